@@ -1,91 +1,1296 @@
-const DEFAULT_C4_ROWS: usize = 6;
-const DEFAULT_C4_COLS: usize = 7;
-const USER: usize = 1;
-const COMPUTER: usize = 2;
-const EMPTY: usize = 0;
+use crate::constant::{
+    COMPUTER, DEFAULT_C4_COLS, DEFAULT_C4_ROWS, DEFAULT_C4_WIN_LENGTH, DEFAULT_SERVER_PORT,
+    HARD_TIME_BUDGET_MS, MAX_PLAYERS, PERFECT_TIME_BUDGET_MS, USER,
+};
+use crate::engine::arena::{
+    elo_ratings, play_match, round_robin, Engine, EngineSpec, Game, MatchResult, Tuned,
+};
+use crate::engine::clock::today;
+use crate::engine::connect4::{
+    search, solve, Connect4Position, Move, Rules, Seat, Topology, Variant, MAX_COLUMNS,
+    MAX_ROWS, MIN_WIN_LENGTH, POP_TEN_TARGET,
+};
+use crate::engine::record::{Connect4Record, TootOttoRecord};
+use crate::engine::tootandotto::{
+    best_move as toot_otto_best_move, random_move, TootOttoPosition, TootOttoRules,
+    MAX_SIZE as TOOT_OTTO_MAX_SIZE,
+};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use crossterm::style::Stylize;
+use rand::prelude::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tui::{Screen, Theme};
 
-use crate::components::connect4board;
+mod server;
+mod tui;
 
+/// How each cell is drawn, indexed by the player whose disc is in it.
+const SYMBOLS: [&str; MAX_PLAYERS + 1] = [".", "X", "O", "#", "@"];
 
-// cli.rs
-pub fn connect4() {
-    let mut board = init_board();
-    let mut current_player = USER; // Start with the user
+/// Play and analyse Connect 4 and TOOT-OTTO in the terminal.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// The colours of the discs and letters, as in the web themes
+    #[arg(long, global = true, value_enum, default_value_t = Theme::Regular)]
+    theme: Theme,
+}
 
-    loop {
-        display_board(&board);
-        let column = get_player_input();
-        
-        if let Some(row) = get_next_open_row(&board, column) {
-            board[row][column] = current_player;
-            if let Some(winner) = connect4board::check_winner(&board) {
-                display_board(&board);
-                println!("Player {} wins!", winner);
-                break;
+#[derive(Subcommand)]
+enum Command {
+    /// Play a game against the computer
+    Play {
+        #[command(subcommand)]
+        game: PlayGame,
+    },
+    /// Show what the computer thinks of a Connect 4 position
+    Analyze(AnalyzeArgs),
+    /// Watch the computer play a game against itself
+    Selfplay {
+        #[command(subcommand)]
+        game: SelfplayGame,
+    },
+    /// Play many games between two engines and compare them
+    Match {
+        #[command(subcommand)]
+        game: MatchGame,
+    },
+    /// Play a round robin between named engines and rate them
+    Tournament {
+        #[command(subcommand)]
+        game: TournamentGame,
+    },
+    /// Host rooms for the web boards to play each other online
+    Serve(ServeArgs),
+}
+
+#[derive(Subcommand)]
+enum PlayGame {
+    /// Connect 4 and its variants
+    Connect4 {
+        #[command(flatten)]
+        options: Connect4Options,
+        /// Who plays the first disc
+        #[arg(long, value_enum, default_value_t = First::User)]
+        first: First,
+        /// How many games to play in a row
+        #[arg(long, default_value_t = 1)]
+        games: usize,
+    },
+    /// TOOT-OTTO
+    TootOtto {
+        #[command(flatten)]
+        options: TootOttoOptions,
+        /// The word you spell
+        #[arg(long, value_enum, default_value_t = Side::Toot, conflicts_with = "load")]
+        side: Side,
+        /// Who places the first letter. A saved game keeps its own, and the
+        /// games after it count from there
+        #[arg(long, value_enum, default_value_t = First::User, conflicts_with = "load")]
+        first: First,
+        /// How many games to play in a row
+        #[arg(long, default_value_t = 1)]
+        games: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum SelfplayGame {
+    /// Connect 4 and its variants
+    Connect4 {
+        #[command(flatten)]
+        options: Connect4Options,
+    },
+    /// TOOT-OTTO
+    TootOtto {
+        #[command(flatten)]
+        options: TootOttoOptions,
+    },
+}
+
+#[derive(Subcommand)]
+enum MatchGame {
+    /// Connect 4 and its variants, for two players
+    Connect4 {
+        #[command(flatten)]
+        board: Connect4Board,
+        #[command(flatten)]
+        options: MatchOptions,
+    },
+    /// TOOT-OTTO, with the random and minimax engines
+    TootOtto {
+        #[command(flatten)]
+        board: TootOttoBoard,
+        #[command(flatten)]
+        options: MatchOptions,
+    },
+}
+
+#[derive(Subcommand)]
+enum TournamentGame {
+    /// Connect 4 and its variants, for two players
+    Connect4 {
+        #[command(flatten)]
+        board: Connect4Board,
+        #[command(flatten)]
+        options: TournamentOptions,
+    },
+    /// TOOT-OTTO, with the random and minimax engines
+    TootOtto {
+        #[command(flatten)]
+        board: TootOttoBoard,
+        #[command(flatten)]
+        options: TournamentOptions,
+    },
+}
+
+#[derive(Args)]
+struct TournamentOptions {
+    /// An engine taking part, as NAME=ENGINE and then any weights to change,
+    /// e.g. wide=minimax:4,one_short=400. Connect 4 weights are center,
+    /// complete, one_short and two_short; TOOT-OTTO weights are win, block,
+    /// advance and block_advance
+    #[arg(long = "entrant", required = true, value_parser = parse_entrant)]
+    entrants: Vec<Entrant>,
+    /// Games per pairing
+    #[arg(long, default_value_t = 20)]
+    games: usize,
+    /// Random moves at the start of every game, so games between the same
+    /// engines differ
+    #[arg(long, default_value_t = 2)]
+    opening_moves: usize,
+    /// Write the Elo table to PREFIX-elo.csv and the pairings to
+    /// PREFIX-crosstable.csv
+    #[arg(long, value_name = "PREFIX")]
+    csv: Option<String>,
+    /// Write the Elo table and the pairings to a JSON file
+    #[arg(long, value_name = "PATH")]
+    json: Option<PathBuf>,
+}
+
+/// A named engine in a tournament.
+#[derive(Clone)]
+struct Entrant {
+    name: String,
+    engine: Tuned,
+}
+
+/// A match has to play at least one game to score anything.
+fn parse_games(games: &str) -> Result<usize, String> {
+    match games.parse() {
+        Ok(games) if games > 0 => Ok(games),
+        _ => Err(format!("'{}' is not a number of games, play at least 1", games)),
+    }
+}
+
+fn parse_entrant(entrant: &str) -> Result<Entrant, String> {
+    let (name, settings) = entrant
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=ENGINE, got '{}'", entrant))?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "'{}' is not a name, use letters, digits, - and _",
+            name
+        ));
+    }
+    let mut settings = settings.split(',');
+    let spec: EngineSpec = settings.next().unwrap_or_default().parse()?;
+    let mut engine = Tuned::from(spec);
+    for weight in settings {
+        let (key, value) = weight
+            .split_once('=')
+            .ok_or_else(|| format!("expected WEIGHT=VALUE, got '{}'", weight))?;
+        let value: isize = value
+            .parse()
+            .map_err(|_| format!("'{}' is not a whole number", value))?;
+        let field = match key {
+            "center" => &mut engine.connect4.center,
+            "complete" => &mut engine.connect4.complete,
+            "one_short" => &mut engine.connect4.one_short,
+            "two_short" => &mut engine.connect4.two_short,
+            "win" => &mut engine.toot_otto.win,
+            "block" => &mut engine.toot_otto.block,
+            "advance" => &mut engine.toot_otto.advance,
+            "block_advance" => &mut engine.toot_otto.block_advance,
+            _ => return Err(format!("unknown weight '{}'", key)),
+        };
+        *field = value;
+    }
+    Ok(Entrant {
+        name: name.to_string(),
+        engine,
+    })
+}
+
+#[derive(Args)]
+struct MatchOptions {
+    /// The engine being measured: random, minimax:DEPTH, timed:MS or perfect:MS
+    #[arg(long, default_value = "minimax:4")]
+    engine: EngineSpec,
+    /// The engine it is measured against
+    #[arg(long, default_value = "random")]
+    against: EngineSpec,
+    #[arg(long, default_value_t = 100, value_parser = parse_games)]
+    games: usize,
+    /// Random moves at the start of every game, so games between the same
+    /// engines differ
+    #[arg(long, default_value_t = 2)]
+    opening_moves: usize,
+}
+
+#[derive(Args)]
+struct Connect4Options {
+    #[command(flatten)]
+    board: Connect4Board,
+    #[arg(long, value_enum, default_value_t = Difficulty::Hard)]
+    difficulty: Difficulty,
+    /// Carry on from a saved game record, on the board it was played on
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["rows", "cols", "win_length", "variant", "cylinder", "players"]
+    )]
+    load: Option<PathBuf>,
+    /// Save the game record to a file when the game ends or you quit. When
+    /// playing several games, the last one is kept
+    #[arg(long, value_name = "PATH")]
+    save: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct Connect4Board {
+    /// Defaults to 6, or the variant's own board
+    #[arg(long)]
+    rows: Option<usize>,
+    /// Defaults to 7, or the variant's own board
+    #[arg(long)]
+    cols: Option<usize>,
+    /// How many discs in a row win the game
+    #[arg(long)]
+    win_length: Option<usize>,
+    #[arg(long, value_enum, default_value_t = VariantArg::Standard)]
+    variant: VariantArg,
+    /// Wrap the left and right edges around so lines can cross them
+    #[arg(long)]
+    cylinder: bool,
+    /// With more than two players the computer plays every other seat
+    #[arg(long, default_value_t = 2)]
+    players: usize,
+}
+
+#[derive(Args)]
+struct TootOttoOptions {
+    #[command(flatten)]
+    board: TootOttoBoard,
+    /// Perfect is not available for TOOT-OTTO
+    #[arg(long, value_enum, default_value_t = Difficulty::Hard)]
+    difficulty: Difficulty,
+    /// Carry on from a saved game record, with its board, letters and sides.
+    /// You are player 1
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["rows", "cols"])]
+    load: Option<PathBuf>,
+    /// Save the game record to a file when the game ends or you quit. When
+    /// playing several games, the last one is kept
+    #[arg(long, value_name = "PATH")]
+    save: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct TootOttoBoard {
+    #[arg(long, default_value_t = TootOttoRules::default().rows)]
+    rows: usize,
+    #[arg(long, default_value_t = TootOttoRules::default().columns)]
+    cols: usize,
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// Columns played so far, counted from 1 as in game records, starting
+    /// with player 1
+    moves: Vec<usize>,
+    #[arg(long, default_value_t = DEFAULT_C4_ROWS)]
+    rows: usize,
+    #[arg(long, default_value_t = DEFAULT_C4_COLS)]
+    cols: usize,
+    /// How long to think, in milliseconds
+    #[arg(long, default_value_t = PERFECT_TIME_BUDGET_MS)]
+    budget_ms: u64,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// The address to listen on, 0.0.0.0 to let other machines in
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    #[arg(long, default_value_t = DEFAULT_SERVER_PORT)]
+    port: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum VariantArg {
+    Standard,
+    Popout,
+    FiveInARow,
+    Pop10,
+}
+
+impl From<VariantArg> for Variant {
+    fn from(variant: VariantArg) -> Self {
+        match variant {
+            VariantArg::Standard => Variant::Standard,
+            VariantArg::Popout => Variant::PopOut,
+            VariantArg::FiveInARow => Variant::FiveInARow,
+            VariantArg::Pop10 => Variant::PopTen,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Difficulty {
+    Easy,
+    Hard,
+    Perfect,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum First {
+    User,
+    Computer,
+    /// Toss a coin before every game
+    Random,
+    /// Take turns going first, starting with you
+    Alternate,
+}
+
+impl First {
+    /// Whether the user moves first in game `game`, counting from 0.
+    fn user_starts(self, game: usize) -> bool {
+        match self {
+            First::User => true,
+            First::Computer => false,
+            First::Random => rand::random(),
+            First::Alternate => game.is_multiple_of(2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Side {
+    Toot,
+    Otto,
+    Random,
+}
+
+/// Entry point of the native build.
+pub fn run() {
+    let cli = Cli::parse();
+    let theme = cli.theme;
+    match cli.command {
+        Command::Play { game } => match game {
+            PlayGame::Connect4 {
+                options,
+                first,
+                games,
+            } => {
+                let (rules, moves) = connect4_start(&options);
+                for game in 0..games {
+                    // The user takes the first or second seat and the
+                    // computer plays all the others
+                    let user_seat = if first.user_starts(game) { 1 } else { 2 };
+                    let seats = (1..=rules.players)
+                        .map(|player| {
+                            if player == user_seat {
+                                Seat::Human
+                            } else {
+                                Seat::Computer
+                            }
+                        })
+                        .collect();
+                    // A saved game only opens the first of them
+                    let moves = if game == 0 { &moves[..] } else { &[] };
+                    if !connect4(rules, moves, seats, &options, theme) {
+                        break;
+                    }
+                }
             }
-        } else {
-            println!("Column is full or invalid, try another one.");
-            continue;
+            PlayGame::TootOtto {
+                options,
+                side,
+                first,
+                games,
+            } => {
+                let (rules, moves) = toot_otto_start(&options);
+                let mut rules = if options.load.is_some() {
+                    rules
+                } else {
+                    let user_is_toot = match side {
+                        Side::Toot => true,
+                        Side::Otto => false,
+                        Side::Random => rand::random(),
+                    };
+                    TootOttoRules {
+                        toot_player: if user_is_toot { USER } else { COMPUTER },
+                        first_player: if first.user_starts(0) { USER } else { COMPUTER },
+                        ..rules
+                    }
+                };
+                for game in 0..games {
+                    let moves = if game == 0 { &moves[..] } else { &[] };
+                    if !toot_otto(rules, moves, Some(USER), &options, theme) {
+                        break;
+                    }
+                    let user_starts = if first == First::Alternate {
+                        // Whoever went second goes first next
+                        rules.first_player != USER
+                    } else {
+                        first.user_starts(game + 1)
+                    };
+                    rules.first_player = if user_starts { USER } else { COMPUTER };
+                }
+            }
+        },
+        Command::Analyze(args) => analyze(&args, theme),
+        Command::Serve(args) => {
+            let address = format!("{}:{}", args.host, args.port);
+            if let Err(error) = server::serve(&address) {
+                Cli::command()
+                    .error(ErrorKind::Io, format!("can't serve on {}: {}", address, error))
+                    .exit();
+            }
+        }
+        Command::Selfplay { game } => match game {
+            SelfplayGame::Connect4 { options } => {
+                let (rules, moves) = connect4_start(&options);
+                let seats = vec![Seat::Computer; rules.players];
+                connect4(rules, &moves, seats, &options, theme);
+            }
+            SelfplayGame::TootOtto { options } => {
+                let (rules, moves) = toot_otto_start(&options);
+                toot_otto(rules, &moves, None, &options, theme);
+            }
+        },
+        Command::Tournament { game } => match game {
+            TournamentGame::Connect4 { board, options } => {
+                let rules = connect4_rules(&board);
+                if rules.players != 2 {
+                    invalid("tournament games are played between two engines".to_string());
+                }
+                tournament(|| Connect4Position::new(rules), &options);
+            }
+            TournamentGame::TootOtto { board, options } => {
+                for entrant in &options.entrants {
+                    if !entrant.engine.spec.plays_toot_otto() {
+                        invalid(format!(
+                            "{} can't play TOOT-OTTO, use random or minimax:DEPTH",
+                            entrant.name
+                        ));
+                    }
+                }
+                let rules = toot_otto_board_rules(&board);
+                tournament(|| TootOttoPosition::new(rules), &options);
+            }
+        },
+        Command::Match { game } => match game {
+            MatchGame::Connect4 { board, options } => {
+                let rules = connect4_rules(&board);
+                if rules.players != 2 {
+                    invalid("matches are played between two engines".to_string());
+                }
+                let result = play_match(
+                    || Connect4Position::new(rules),
+                    &mut { options.engine },
+                    &mut { options.against },
+                    options.games,
+                    options.opening_moves,
+                    |result| report_progress(result, options.games),
+                );
+                report_match(&options, &result);
+            }
+            MatchGame::TootOtto { board, options } => {
+                for engine in [options.engine, options.against] {
+                    if !engine.plays_toot_otto() {
+                        invalid(format!("{} can't play TOOT-OTTO, use random or minimax:DEPTH", engine));
+                    }
+                }
+                let rules = toot_otto_board_rules(&board);
+                let result = play_match(
+                    || TootOttoPosition::new(rules),
+                    &mut { options.engine },
+                    &mut { options.against },
+                    options.games,
+                    options.opening_moves,
+                    |result| report_progress(result, options.games),
+                );
+                report_match(&options, &result);
+            }
+        },
+    }
+}
+
+fn tournament<G: Game>(new_game: impl Fn() -> G, options: &TournamentOptions)
+where
+    Tuned: Engine<G>,
+{
+    let entrants = &options.entrants;
+    if entrants.len() < 2 {
+        invalid("a tournament needs at least two entrants".to_string());
+    }
+    for (i, entrant) in entrants.iter().enumerate() {
+        if entrants[..i].iter().any(|other| other.name == entrant.name) {
+            invalid(format!("two entrants are called {}", entrant.name));
         }
+    }
+    let mut engines: Vec<Tuned> = entrants.iter().map(|entrant| entrant.engine).collect();
+    let results = round_robin(
+        new_game,
+        &mut engines,
+        options.games,
+        options.opening_moves,
+        |i, j, result| {
+            println!(
+                "{} against {}: {} wins, {} draws, {} losses",
+                entrants[i].name, entrants[j].name, result.wins, result.draws, result.losses
+            );
+        },
+    );
+    let ratings = elo_ratings(&results);
+    // Entrants by rating, best first
+    let mut ranking: Vec<usize> = (0..entrants.len()).collect();
+    ranking.sort_by(|&a, &b| ratings[b].total_cmp(&ratings[a]));
+    let total = |i: usize| {
+        results[i]
+            .iter()
+            .fold(MatchResult::default(), |total, result| MatchResult {
+                wins: total.wins + result.wins,
+                draws: total.draws + result.draws,
+                losses: total.losses + result.losses,
+            })
+    };
 
-        current_player = if current_player == USER { COMPUTER } else { USER };
-        
-        // Handle computer move
-        if current_player == COMPUTER {
-            make_computer_move(&mut board);
-            if let Some(winner) = connect4board::check_winner(&board) {
-                display_board(&board);
-                println!("Computer wins!");
-                break;
+    println!();
+    println!(
+        "{:<4} {:<16} {:>6} {:>6} {:>7}",
+        "Rank", "Entrant", "Elo", "Games", "Score"
+    );
+    for (rank, &i) in ranking.iter().enumerate() {
+        let total = total(i);
+        println!(
+            "{:<4} {:<16} {:>+6.0} {:>6} {:>6.1}%",
+            rank + 1,
+            entrants[i].name,
+            ratings[i],
+            total.games(),
+            total.score() * 100.0
+        );
+    }
+    println!();
+    print!("{:<16}", "");
+    for &j in &ranking {
+        print!(" {:>10}", entrants[j].name);
+    }
+    println!();
+    for &i in &ranking {
+        print!("{:<16}", entrants[i].name);
+        for &j in &ranking {
+            if i == j {
+                print!(" {:>10}", "-");
+            } else {
+                let result = results[i][j];
+                let points = result.wins as f64 + result.draws as f64 / 2.0;
+                print!(" {:>10}", format!("{}/{}", points, result.games()));
             }
-            current_player = USER; // Switch back to user
         }
+        println!();
+    }
+
+    let pairings: Vec<(usize, usize, MatchResult)> = ranking
+        .iter()
+        .flat_map(|&i| {
+            ranking
+                .iter()
+                .filter(move |&&j| j != i)
+                .map(move |&j| (i, j))
+        })
+        .map(|(i, j)| (i, j, results[i][j]))
+        .collect();
+    if let Some(prefix) = &options.csv {
+        let mut elo = String::from("rank,entrant,engine,elo,games,wins,draws,losses,score\n");
+        for (rank, &i) in ranking.iter().enumerate() {
+            let total = total(i);
+            elo += &format!(
+                "{},{},{},{:.1},{},{},{},{},{:.4}\n",
+                rank + 1,
+                entrants[i].name,
+                entrants[i].engine.spec,
+                ratings[i],
+                total.games(),
+                total.wins,
+                total.draws,
+                total.losses,
+                total.score()
+            );
+        }
+        let mut crosstable = String::from("entrant,opponent,games,wins,draws,losses,score\n");
+        for &(i, j, result) in &pairings {
+            crosstable += &format!(
+                "{},{},{},{},{},{},{:.4}\n",
+                entrants[i].name,
+                entrants[j].name,
+                result.games(),
+                result.wins,
+                result.draws,
+                result.losses,
+                result.score()
+            );
+        }
+        write_report(&PathBuf::from(format!("{}-elo.csv", prefix)), &elo);
+        write_report(
+            &PathBuf::from(format!("{}-crosstable.csv", prefix)),
+            &crosstable,
+        );
+    }
+    if let Some(path) = &options.json {
+        let elo: Vec<_> = ranking
+            .iter()
+            .map(|&i| {
+                let total = total(i);
+                serde_json::json!({
+                    "entrant": entrants[i].name,
+                    "engine": entrants[i].engine.spec.to_string(),
+                    "elo": ratings[i],
+                    "games": total.games(),
+                    "wins": total.wins,
+                    "draws": total.draws,
+                    "losses": total.losses,
+                    "score": total.score(),
+                })
+            })
+            .collect();
+        let crosstable: Vec<_> = pairings
+            .iter()
+            .map(|&(i, j, result)| {
+                serde_json::json!({
+                    "entrant": entrants[i].name,
+                    "opponent": entrants[j].name,
+                    "games": result.games(),
+                    "wins": result.wins,
+                    "draws": result.draws,
+                    "losses": result.losses,
+                    "score": result.score(),
+                })
+            })
+            .collect();
+        let report = serde_json::json!({ "elo": elo, "crosstable": crosstable });
+        let json = serde_json::to_string_pretty(&report).expect("Failed to write JSON");
+        write_report(path, &json);
+    }
+}
+
+fn write_report(path: &Path, contents: &str) {
+    if let Err(error) = std::fs::write(path, contents) {
+        Cli::command()
+            .error(
+                ErrorKind::Io,
+                format!("can't write {}: {}", path.display(), error),
+            )
+            .exit();
+    }
+    println!("Wrote {}", path.display());
+}
+
+/// Keeps a running tally on one line while a match is played.
+fn report_progress(result: &MatchResult, games: usize) {
+    print!(
+        "\rGame {}/{}: {} wins, {} draws, {} losses",
+        result.games(),
+        games,
+        result.wins,
+        result.draws,
+        result.losses
+    );
+    std::io::stdout().flush().expect("Failed to flush stdout");
+}
+
+fn report_match(options: &MatchOptions, result: &MatchResult) {
+    let (low, high) = result.score_interval();
+    let (elo_low, elo_high) = result.elo_interval();
+    println!();
+    println!(
+        "{} against {} over {} games:",
+        options.engine,
+        options.against,
+        result.games()
+    );
+    println!(
+        "  {} wins, {} draws, {} losses",
+        result.wins, result.draws, result.losses
+    );
+    println!(
+        "  score {:.1}% (95% confidence interval {:.1}% to {:.1}%)",
+        result.score() * 100.0,
+        low * 100.0,
+        high * 100.0
+    );
+    match result.elo_difference() {
+        Some(elo) => println!(
+            "  Elo difference {:+.0} (95% confidence interval {:+.0} to {:+.0})",
+            elo, elo_low, elo_high
+        ),
+        // A clean sweep only bounds the difference from one side
+        None if result.losses == 0 => println!(
+            "  Elo difference unbounded, at least {:+.0} with 95% confidence",
+            elo_low
+        ),
+        None => println!(
+            "  Elo difference unbounded, at most {:+.0} with 95% confidence",
+            elo_high
+        ),
+    }
+    if low > 0.5 {
+        println!("  {} is stronger.", options.engine);
+    } else if high < 0.5 {
+        println!("  {} is stronger.", options.against);
+    } else {
+        println!("  Not enough games to tell the two apart.");
     }
 }
 
+/// Exits with a usage error, the way clap reports invalid arguments.
+fn invalid(message: String) -> ! {
+    Cli::command().error(ErrorKind::ValueValidation, message).exit()
+}
+
+fn connect4_rules(options: &Connect4Board) -> Rules {
+    let variant = Variant::from(options.variant);
+    // Some variants come with a board of their own
+    if let Some(fixed) = variant.fixed_rules() {
+        if options.rows.is_some() || options.cols.is_some() || options.win_length.is_some() {
+            invalid(format!("{} has a board of its own", variant.name()));
+        }
+        if options.cylinder || options.players != 2 {
+            invalid(format!("{} is played by two players on a flat board", variant.name()));
+        }
+        return fixed;
+    }
+    let rows = options.rows.unwrap_or(DEFAULT_C4_ROWS);
+    let columns = options.cols.unwrap_or(DEFAULT_C4_COLS);
+    let win_length = options.win_length.unwrap_or(DEFAULT_C4_WIN_LENGTH);
+    if !(4..=MAX_ROWS).contains(&rows) || !(4..=MAX_COLUMNS).contains(&columns) {
+        invalid(format!("the board must be between 4x4 and {}x{}", MAX_ROWS, MAX_COLUMNS));
+    }
+    if !(MIN_WIN_LENGTH..=rows.max(columns)).contains(&win_length) {
+        invalid(format!(
+            "the win length must be between {} and {}",
+            MIN_WIN_LENGTH,
+            rows.max(columns)
+        ));
+    }
+    if !(2..=MAX_PLAYERS).contains(&options.players) {
+        invalid(format!("between 2 and {} players can play", MAX_PLAYERS));
+    }
+    Rules {
+        rows,
+        columns,
+        win_length,
+        variant,
+        topology: if options.cylinder {
+            Topology::Cylinder
+        } else {
+            Topology::Flat
+        },
+        players: options.players,
+    }
+}
 
-fn init_board() -> Vec<Vec<usize>> {
-    vec![vec![EMPTY; DEFAULT_C4_COLS]; DEFAULT_C4_ROWS]
+fn toot_otto_rules(options: &TootOttoOptions) -> TootOttoRules {
+    if options.difficulty == Difficulty::Perfect {
+        invalid("TOOT-OTTO has no perfect player, use easy or hard".to_string());
+    }
+    toot_otto_board_rules(&options.board)
 }
 
-fn display_board(board: &Vec<Vec<usize>>) {
-    for row in board {
-        for &cell in row {
-            let symbol = match cell {
-                USER => "X",
-                COMPUTER => "O",
-                _ => ".",
+/// The rules and the letters already played, from a saved record if there
+/// is one.
+fn toot_otto_start(options: &TootOttoOptions) -> (TootOttoRules, Vec<(usize, char)>) {
+    let rules = toot_otto_rules(options);
+    match &options.load {
+        Some(path) => {
+            let record: TootOttoRecord = read_record(path);
+            (record.rules, record.moves)
+        }
+        None => (rules, Vec::new()),
+    }
+}
+
+fn toot_otto_board_rules(board: &TootOttoBoard) -> TootOttoRules {
+    let sizes = 4..=TOOT_OTTO_MAX_SIZE;
+    if !sizes.contains(&board.rows) || !sizes.contains(&board.cols) {
+        invalid(format!(
+            "the board must be between 4x4 and {0}x{0}",
+            TOOT_OTTO_MAX_SIZE
+        ));
+    }
+    TootOttoRules::for_board(board.rows, board.cols)
+}
+
+/// The rules and the moves already played, from a saved record if there is
+/// one.
+fn connect4_start(options: &Connect4Options) -> (Rules, Vec<Move>) {
+    match &options.load {
+        Some(path) => {
+            let record: Connect4Record = read_record(path);
+            (record.rules, record.moves)
+        }
+        None => (connect4_rules(&options.board), Vec::new()),
+    }
+}
+
+/// Reads a game record, exiting with the reason if it can't be used.
+fn read_record<R: FromStr<Err = String>>(path: &Path) -> R {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|error| {
+        Cli::command()
+            .error(ErrorKind::Io, format!("can't read {}: {}", path.display(), error))
+            .exit()
+    });
+    text.parse()
+        .unwrap_or_else(|error| invalid(format!("{}: {}", path.display(), error)))
+}
+
+/// Plays a game of Connect 4 that starts with `moves`, which must be legal.
+/// Returns `false` if the user quit before the end.
+fn connect4(
+    rules: Rules,
+    moves: &[Move],
+    seats: Vec<Seat>,
+    options: &Connect4Options,
+    theme: Theme,
+) -> bool {
+    let difficulty = options.difficulty;
+    let mut board = Connect4Position::new(rules);
+    // Only a human needs the full screen, computers alone just print moves
+    let mut screen = if seats.contains(&Seat::Human) {
+        Screen::open(theme)
+    } else {
+        None
+    };
+    let name = |player: usize| match seats[player - 1] {
+        Seat::Human => format!("Player {} ({})", player, SYMBOLS[player]),
+        Seat::Computer => format!("Computer ({})", SYMBOLS[player]),
+    };
+    let mut history: Vec<String> = Vec::new();
+    for &mv in moves {
+        history.push(history_entry(board.current_player(), mv));
+        board.play(mv);
+    }
+    let mut message = String::new();
+
+    // `None` if the user quit before the end
+    let outcome = loop {
+        if let Some(winner) = board.winner() {
+            break Some(format!("{} wins!", name(winner)));
+        }
+        if board.is_draw() {
+            break Some("It's a draw!".to_string());
+        }
+        // Players don't simply alternate: in Pop 10 a player may have several
+        // moves in a row, and computers may sit next to each other
+        let player = board.current_player();
+        if screen.is_none() {
+            display_board(&board, theme);
+        }
+        let mv = if seats[player - 1] == Seat::Human {
+            let prompt = format!("{} to move", name(player));
+            let mv = match &mut screen {
+                Some(screen) => screen.connect4_move(&board, &history, &prompt, &message),
+                None => {
+                    if !message.is_empty() {
+                        println!("{}", message.as_str().red());
+                    }
+                    get_player_input(&board)
+                }
             };
-            print!("{} ", symbol);
+            let Some(mv) = mv else {
+                break None;
+            };
+            if !board.play(mv) {
+                message = illegal_move(&board, mv);
+                continue;
+            }
+            mv
+        } else {
+            if let Some(screen) = &mut screen {
+                screen.show_connect4(&board, &history, &format!("{} is thinking...", name(player)));
+            }
+            let Some(mv) = make_computer_move(&mut board, difficulty) else {
+                break Some("It's a draw!".to_string());
+            };
+            if screen.is_none() {
+                match mv {
+                    Move::Drop(col) => println!("{} picked column: {}", name(player), col + 1),
+                    Move::Pop(col) => println!("{} popped column: {}", name(player), col + 1),
+                }
+            }
+            mv
+        };
+        message.clear();
+        history.push(history_entry(player, mv));
+    };
+
+    match (&mut screen, &outcome) {
+        (Some(screen), Some(outcome)) => {
+            screen.show_connect4(&board, &history, &format!("{} Press any key.", outcome));
+            screen.wait_for_key();
+        }
+        (None, Some(outcome)) => {
+            display_board(&board, theme);
+            println!("{}", outcome);
+        }
+        (_, None) => {}
+    }
+    // The screen gives the terminal back as it is dropped
+    drop(screen);
+    if let Some(path) = &options.save {
+        let names = (1..=board.players()).map(name).collect();
+        let record = Connect4Record::new(&board, names, today());
+        write_report(path, &record.to_string());
+    }
+    outcome.is_some()
+}
+
+/// How a move is shown in the history beside the board, with the column
+/// counted from 1 as in game records.
+fn history_entry(player: usize, mv: Move) -> String {
+    match mv {
+        Move::Drop(col) => format!("{}{}", SYMBOLS[player], col + 1),
+        Move::Pop(col) => format!("{}p{}", SYMBOLS[player], col + 1),
+    }
+}
+
+/// Why `mv` can't be played, for the player who just tried it.
+fn illegal_move(board: &Connect4Position, mv: Move) -> String {
+    match mv {
+        Move::Drop(col) | Move::Pop(col) if col >= board.columns() => {
+            format!("There is no column {}, pick one from 1 to {}.", col + 1, board.columns())
+        }
+        Move::Drop(col) if board.next_open_row(col).is_none() => {
+            format!("Column {} is full, pick another one.", col + 1)
+        }
+        Move::Drop(_) if board.variant() == Variant::PopTen && board.is_setup() => {
+            "While setting up Pop 10, fill the lowest columns first.".to_string()
+        }
+        Move::Drop(_) => "You have to pop one of your discs first.".to_string(),
+        Move::Pop(_) if !matches!(board.variant(), Variant::PopOut | Variant::PopTen) => {
+            format!("Discs can't be popped in {}.", board.variant().name())
+        }
+        Move::Pop(col) => format!("You can't pop column {} right now.", col + 1),
+    }
+}
+
+fn analyze(args: &AnalyzeArgs, theme: Theme) {
+    let rules = Rules {
+        rows: args.rows,
+        columns: args.cols,
+        ..Rules::default()
+    };
+    if !(4..=MAX_ROWS).contains(&rules.rows) || !(4..=MAX_COLUMNS).contains(&rules.columns) {
+        invalid(format!("the board must be between 4x4 and {}x{}", MAX_ROWS, MAX_COLUMNS));
+    }
+    let mut board = Connect4Position::new(rules);
+    for (i, &col) in args.moves.iter().enumerate() {
+        let played = col >= 1 && board.winner().is_none() && board.play(Move::Drop(col - 1));
+        if !played {
+            invalid(format!("move {} (column {}) cannot be played", i + 1, col));
+        }
+    }
+    display_board(&board, theme);
+
+    if let Some(winner) = board.winner() {
+        println!("Player {} has won.", winner);
+        return;
+    }
+    if board.is_draw() {
+        println!("The game is a draw.");
+        return;
+    }
+    let player = board.current_player();
+    println!("Player {} ({}) to move.", player, SYMBOLS[player]);
+    match solve(&mut board, args.budget_ms) {
+        Some(solution) => {
+            match solution.winner {
+                Some(winner) => println!(
+                    "Player {} wins with perfect play in {} more discs.",
+                    winner, solution.moves_to_end
+                ),
+                None => println!("The game is a draw with perfect play."),
+            }
+            println!("Best column: {}", solution.column + 1);
+        }
+        None => {
+            let (best_move, score) = search(&mut board, args.budget_ms);
+            println!("Too early to solve in time, so this is a heuristic search.");
+            println!("Best column: {} (score {:+})", best_move.column() + 1, score);
+        }
+    }
+}
+
+fn display_board(board: &Connect4Position, theme: Theme) {
+    let symbol = |row, col| match board.cell(row, col) {
+        0 => SYMBOLS[0].to_string(),
+        player => SYMBOLS[player].with(theme.chip(player)).bold().to_string(),
+    };
+    let wraps = board.topology() == Topology::Cylinder;
+    for row in 0..board.rows() {
+        // Repeat the opposite edge on either side so wrapped lines show up
+        if wraps {
+            print!("{} | ", symbol(row, board.columns() - 1));
+        }
+        for col in 0..board.columns() {
+            print!("{} ", symbol(row, col));
+        }
+        if wraps {
+            print!("| {}", symbol(row, 0));
         }
         println!();
     }
+    if board.variant() == Variant::PopTen {
+        let collected: Vec<String> = (1..=board.players())
+            .map(|player| format!("{} {}", SYMBOLS[player], board.collected(player)))
+            .collect();
+        println!("Collected: {} (first to {})", collected.join(" - "), POP_TEN_TARGET);
+    }
+}
+
+/// Reads a move typed as a column counted from 1, or p and a column for a
+/// pop, asking again until the input makes sense. Returns `None` on q or end
+/// of input.
+fn get_player_input(board: &Connect4Position) -> Option<Move> {
+    let last = board.columns();
+    if board.variant() == Variant::PopTen && !board.is_setup() {
+        if board.must_drop() {
+            println!("Drop the disc you popped back into a column (1 to {}):", last);
+        } else {
+            println!("Pop one of your discs from the bottom row (p1 to p{}):", last);
+        }
+    } else if board.variant() == Variant::PopOut {
+        println!(
+            "Enter a column number to drop (1 to {0}) or p and a column to pop (p1 to p{0}):",
+            last
+        );
+    } else {
+        println!("Enter a column number (1 to {}), or q to quit:", last);
+    }
+    loop {
+        let input = read_line()?.to_lowercase();
+        let (col, is_pop) = match input.strip_prefix('p') {
+            Some(col) => (col.trim(), true),
+            None => (input.as_str(), false),
+        };
+        match col.parse::<usize>() {
+            Ok(col) if col >= 1 && is_pop => return Some(Move::Pop(col - 1)),
+            Ok(col) if col >= 1 => return Some(Move::Drop(col - 1)),
+            _ => println!("'{}' is not a column number, try again (1 to {}):", input, last),
+        }
+    }
 }
 
-fn get_player_input() -> usize {
-    println!("Enter a column number (0 to {}):", DEFAULT_C4_COLS - 1);
+/// A trimmed line from stdin, or `None` if it was q or the input ran out.
+fn read_line() -> Option<String> {
+    std::io::stdout().flush().expect("Failed to flush stdout");
     let mut input = String::new();
-    std::io::stdin().read_line(&mut input).expect("Failed to read line");
-    input.trim().parse::<usize>().unwrap_or(usize::MAX) // returns usize::MAX on parse failure
+    let read = std::io::stdin().read_line(&mut input).expect("Failed to read line");
+    let input = input.trim();
+    if read == 0 || input.eq_ignore_ascii_case("q") {
+        None
+    } else {
+        Some(input.to_string())
+    }
+}
+
+/// Plays and returns the computer's move, or `None` if it has none.
+fn make_computer_move(board: &mut Connect4Position, difficulty: Difficulty) -> Option<Move> {
+    let best_move = match difficulty {
+        Difficulty::Easy => *board.legal_moves().choose(&mut rand::thread_rng())?,
+        // Too early in the game to solve in time, so fall back to Hard
+        Difficulty::Perfect => match solve(board, PERFECT_TIME_BUDGET_MS) {
+            Some(solution) => Move::Drop(solution.column),
+            None => search(board, HARD_TIME_BUDGET_MS).0,
+        },
+        Difficulty::Hard => search(board, HARD_TIME_BUDGET_MS).0,
+    };
+    board.play(best_move).then_some(best_move)
 }
 
-fn get_next_open_row(board: &Vec<Vec<usize>>, col: usize) -> Option<usize> {
-    if col >= DEFAULT_C4_COLS {
-        return None;
+/// Plays a game of TOOT-OTTO that starts with `moves`, which must be legal,
+/// with the user as `human`, or with the computer on both sides if there is
+/// none. Returns `false` if the user quit before the end.
+fn toot_otto(
+    rules: TootOttoRules,
+    moves: &[(usize, char)],
+    human: Option<usize>,
+    options: &TootOttoOptions,
+    theme: Theme,
+) -> bool {
+    let difficulty = options.difficulty;
+    let mut board = TootOttoPosition::new(rules);
+    let mut screen = human.and_then(|_| Screen::open(theme));
+    let name = |player| match human {
+        Some(human) if human == player => "You".to_string(),
+        Some(_) => "Computer".to_string(),
+        None => format!("Computer {}", player),
+    };
+    if screen.is_none() {
+        println!(
+            "{} spells {} and {} spells {}.",
+            name(USER),
+            board.word(USER).name(),
+            name(COMPUTER),
+            board.word(COMPUTER).name()
+        );
+    }
+    let mut history: Vec<String> = Vec::new();
+    for &(col, letter) in moves {
+        board.play(col, letter);
+        history.push(format!("{}{}", col + 1, letter));
     }
-    for row in (0..DEFAULT_C4_ROWS).rev() {
-        if board[row][col] == EMPTY {
-            return Some(row);
+    let mut message = String::new();
+
+    // `None` if the user quit before the end
+    let outcome = loop {
+        if let Some(winner) = board.winner() {
+            break Some(format!("{} won with {}!", name(winner), board.word(winner).name()));
+        }
+        if board.is_draw() {
+            break Some("It's a draw!".to_string());
+        }
+        let player = board.current_player();
+        if screen.is_none() {
+            display_toot_otto_board(&board, &name, theme);
+        }
+        let (col, letter) = if human == Some(player) {
+            let prompt = format!("Your move as {}", board.word(player).name());
+            let mv = match &mut screen {
+                Some(screen) => screen.toot_otto_move(&board, &name, &history, &prompt, &message),
+                None => {
+                    if !message.is_empty() {
+                        println!("{}", message.as_str().red());
+                    }
+                    get_toot_otto_input(&board)
+                }
+            };
+            let Some((col, letter)) = mv else {
+                break None;
+            };
+            if board.play(col, letter).is_none() {
+                message = illegal_letter(&board, col, letter);
+                continue;
+            }
+            (col, letter)
+        } else {
+            if let Some(screen) = &mut screen {
+                let status = format!("{} is thinking...", name(player));
+                screen.show_toot_otto(&board, &name, &history, &status);
+            }
+            let computer_move = if difficulty == Difficulty::Easy {
+                random_move(&board)
+            } else {
+                toot_otto_best_move(&board)
+            };
+            let Some((col, letter)) = computer_move else {
+                break Some("It's a draw!".to_string());
+            };
+            board.play(col, letter);
+            if screen.is_none() {
+                println!("{} placed {} in column: {}", name(player), letter, col + 1);
+            }
+            (col, letter)
+        };
+        message.clear();
+        history.push(format!("{}{}", col + 1, letter));
+    };
+
+    match (&mut screen, &outcome) {
+        (Some(screen), Some(outcome)) => {
+            let status = format!("{} Press any key.", outcome);
+            screen.show_toot_otto(&board, &name, &history, &status);
+            screen.wait_for_key();
         }
+        (None, Some(outcome)) => {
+            display_toot_otto_board(&board, &name, theme);
+            println!("{}", outcome);
+        }
+        (_, None) => {}
+    }
+    // The screen gives the terminal back as it is dropped
+    drop(screen);
+    if let Some(path) = &options.save {
+        let names = vec![name(USER), name(COMPUTER)];
+        let record = TootOttoRecord::new(&board, names, today());
+        write_report(path, &record.to_string());
+    }
+    outcome.is_some()
+}
+
+/// Why `letter` can't go in `col`, for the player who just tried it.
+fn illegal_letter(board: &TootOttoPosition, col: usize, letter: char) -> String {
+    if col >= board.columns() {
+        format!("There is no column {}, pick one from 1 to {}.", col + 1, board.columns())
+    } else if board.next_open_row(col).is_none() {
+        format!("Column {} is full, pick another one.", col + 1)
+    } else if letter != 'T' && letter != 'O' {
+        format!("'{}' is not a letter in this game, play T or O.", letter)
+    } else {
+        format!("You have no {}'s left.", letter)
     }
-    None
 }
 
-pub fn make_computer_move(board: &mut Vec<Vec<usize>>) {
+fn display_toot_otto_board(board: &TootOttoPosition, name: &dyn Fn(usize) -> String, theme: Theme) {
+    for row in 0..board.rows() {
+        for col in 0..board.columns() {
+            match board.cell(row, col) {
+                (' ', _) => print!(". "),
+                (letter, player) => print!("{} ", letter.with(theme.chip(player)).bold()),
+            }
+        }
+        println!();
+    }
+    for col in 1..=board.columns() {
+        print!("{} ", col);
+    }
+    println!();
+    for player in [USER, COMPUTER] {
+        println!(
+            "{} ({}): {} T's and {} O's left",
+            name(player).with(theme.chip(player)).bold(),
+            board.word(player).name(),
+            board.letters_left(player, 'T'),
+            board.letters_left(player, 'O')
+        );
+    }
+}
 
-    let (best_col, _) = connect4board::minimax(board, 4, isize::MIN, isize::MAX, true);
-    if let Some(row) = get_next_open_row(board, best_col) {
-        println!("Computer picked column: {}", best_col);
-        board[row][best_col] = COMPUTER;
+/// Reads a letter followed by a column counted from 1, asking again until
+/// the input makes sense. Returns `None` on q or end of input.
+fn get_toot_otto_input(board: &TootOttoPosition) -> Option<(usize, char)> {
+    println!(
+        "Enter a letter and a column, e.g. T1 or O{}, or q to quit:",
+        board.columns()
+    );
+    loop {
+        let input = read_line()?.to_uppercase();
+        let mut chars = input.chars();
+        let letter = chars.next().unwrap_or(' ');
+        match chars.as_str().trim().parse::<usize>() {
+            Ok(col) if col >= 1 => return Some((col - 1, letter)),
+            _ => println!("'{}' is not a letter and a column, try again (e.g. T1):", input),
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,740 @@
+//! A WebSocket server hosting Connect 4 and TOOT-OTTO rooms, so the web
+//! boards can play each other from different machines.
+//!
+//! Every connection gets a thread of its own. Rooms live in one shared lobby,
+//! and each seat keeps a channel to whichever connection sits in it, so a
+//! move on one connection reaches all the others.
+
+use crate::constant::{COMPUTER, USER};
+use crate::engine::connect4::Move;
+use crate::engine::online::{ClientMessage, OnlineGame, ServerMessage};
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::Message;
+
+/// How long a connection waits for a message of its own before passing on
+/// the ones meant for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a room nobody is connected to is kept for its players to come
+/// back.
+const ABANDONED_ROOM: Duration = Duration::from_secs(30 * 60);
+
+/// Room codes leave out I and O, which look like 1 and 0.
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+const CODE_LENGTH: usize = 5;
+
+/// A player sitting in a room, connected or not. Their name is in the
+/// room's record.
+struct Player {
+    /// Proves the seat is theirs when they reconnect.
+    token: String,
+    /// The connection they are playing on and its outgoing messages, or
+    /// `None` while they are away.
+    connection: Option<(usize, Sender<String>)>,
+}
+
+struct Room {
+    game: OnlineGame,
+    /// One seat per player, in turn order.
+    seats: Vec<Option<Player>>,
+    /// When the last player left, if nobody is connected.
+    abandoned_at: Option<Instant>,
+}
+
+impl Room {
+    /// Whose turn it is, or `None` once the game is over.
+    fn player_to_move(&self) -> Option<usize> {
+        match &self.game {
+            OnlineGame::Connect4(record) => {
+                let position = record.position().ok()?;
+                (position.winner().is_none() && !position.is_draw())
+                    .then(|| position.current_player())
+            }
+            OnlineGame::TootOtto(record) => {
+                let position = record.position().ok()?;
+                (position.winner().is_none() && !position.is_draw())
+                    .then(|| position.current_player())
+            }
+        }
+    }
+
+    /// Plays a move for the player in `seat`, once everyone has sat down.
+    fn play(&mut self, seat: usize, message: ClientMessage) -> Result<(), String> {
+        if self.seats.iter().any(Option::is_none) {
+            return Err("wait for everyone to join first".to_string());
+        }
+        match self.player_to_move() {
+            None => return Err("the game is over".to_string()),
+            Some(player) if player != seat + 1 => return Err("it's not your turn".to_string()),
+            Some(_) => {}
+        }
+        match (&mut self.game, message) {
+            (OnlineGame::Connect4(record), ClientMessage::Connect4Move(mv)) => {
+                let mut position = record.position()?;
+                if !position.play(mv) {
+                    return Err(format!("{} can't be played", describe_move(mv)));
+                }
+                record.moves.push(mv);
+            }
+            (OnlineGame::TootOtto(record), ClientMessage::TootOttoMove(col, letter)) => {
+                let mut position = record.position()?;
+                if position.play(col, letter).is_none() {
+                    return Err(format!("{} can't go in column {}", letter, col + 1));
+                }
+                record.moves.push((col, letter));
+            }
+            _ => return Err("that's a move for the other game".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Clears the board for a new game. Swapping who goes first moves every
+    /// Connect 4 player up a seat, and hands the first TOOT-OTTO letter to
+    /// the other player.
+    fn play_again(&mut self, swap_first: bool) -> Result<(), String> {
+        if self.player_to_move().is_some() {
+            return Err("the game isn't over yet".to_string());
+        }
+        match &mut self.game {
+            OnlineGame::Connect4(record) => {
+                record.moves.clear();
+                if swap_first {
+                    self.seats.rotate_left(1);
+                    record.names.rotate_left(1);
+                }
+            }
+            OnlineGame::TootOtto(record) => {
+                record.moves.clear();
+                if swap_first {
+                    record.rules.first_player = if record.rules.first_player == USER {
+                        COMPUTER
+                    } else {
+                        USER
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn seat_of(&self, token: &str) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|player| player.as_ref().is_some_and(|player| player.token == token))
+    }
+
+    /// Puts a player in a free seat and names them in the record.
+    fn sit(&mut self, seat: usize, name: String, connection: (usize, Sender<String>)) -> String {
+        let name = if name.trim().is_empty() {
+            format!("Player {}", seat + 1)
+        } else {
+            name.trim().to_string()
+        };
+        let token: String = (0..16)
+            .map(|_| char::from_digit(thread_rng().gen_range(0..16), 16).unwrap())
+            .collect();
+        match &mut self.game {
+            OnlineGame::Connect4(record) => record.names[seat] = name.clone(),
+            OnlineGame::TootOtto(record) => record.names[seat] = name.clone(),
+        }
+        self.seats[seat] = Some(Player {
+            token: token.clone(),
+            connection: Some(connection),
+        });
+        self.abandoned_at = None;
+        token
+    }
+
+    /// Sends a message to everyone connected.
+    fn broadcast(&self, message: &ServerMessage) {
+        let text = message.to_string();
+        for (_, outbox) in self
+            .seats
+            .iter()
+            .flatten()
+            .filter_map(|player| player.connection.as_ref())
+        {
+            // A connection that just closed has nobody left to tell
+            let _ = outbox.send(text.clone());
+        }
+    }
+
+    /// Tells everyone the seat they hold, and then the state of the game.
+    fn broadcast_seats(&self, code: &str) {
+        for (seat, player) in self.seats.iter().enumerate() {
+            if let Some(Player {
+                token,
+                connection: Some((_, outbox)),
+            }) = player
+            {
+                let _ = outbox.send(
+                    ServerMessage::Joined {
+                        code: code.to_string(),
+                        player: seat + 1,
+                        token: token.clone(),
+                    }
+                    .to_string(),
+                );
+            }
+        }
+        self.broadcast_state();
+    }
+
+    fn broadcast_state(&self) {
+        self.broadcast(&ServerMessage::State {
+            game: self.game.clone(),
+            open_seats: self.seats.iter().filter(|player| player.is_none()).count(),
+        });
+    }
+
+    /// Marks a connection as gone from its seat, keeping the seat for when
+    /// it comes back.
+    fn leave(&mut self, connection: usize) {
+        for player in self.seats.iter_mut().flatten() {
+            if player
+                .connection
+                .as_ref()
+                .is_some_and(|(id, _)| *id == connection)
+            {
+                player.connection = None;
+            }
+        }
+        let connected = self
+            .seats
+            .iter()
+            .flatten()
+            .any(|player| player.connection.is_some());
+        if !connected && self.abandoned_at.is_none() {
+            self.abandoned_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Every room on the server. The connections share one, and tell it what
+/// their boards ask for.
+#[derive(Default)]
+struct Lobby {
+    rooms: HashMap<String, Room>,
+}
+
+/// A board as the lobby sees it: its connection, and where it sits.
+struct Client {
+    id: usize,
+    outbox: Sender<String>,
+    /// The room code and seat token of this connection.
+    place: Option<(String, String)>,
+}
+
+impl Client {
+    fn new(id: usize, outbox: Sender<String>) -> Self {
+        Client {
+            id,
+            outbox,
+            place: None,
+        }
+    }
+
+    fn connection(&self) -> (usize, Sender<String>) {
+        (self.id, self.outbox.clone())
+    }
+}
+
+impl Lobby {
+    /// Carries out one request, or says why it can't be.
+    fn handle(&mut self, client: &mut Client, message: ClientMessage) -> Result<(), String> {
+        match message {
+            ClientMessage::Create(mut game) => {
+                let name = game.names().first().cloned().unwrap_or_default();
+                // Checks the record replays, and empties the seats
+                match &mut game {
+                    OnlineGame::Connect4(record) => {
+                        record.position()?;
+                        record.names = vec![String::new(); record.rules.players];
+                    }
+                    OnlineGame::TootOtto(record) => {
+                        record.position()?;
+                        record.names = vec![String::new(); 2];
+                    }
+                }
+                self.leave(client);
+                let code = loop {
+                    let code: String = (0..CODE_LENGTH)
+                        .map(|_| char::from(*CODE_LETTERS.choose(&mut thread_rng()).unwrap()))
+                        .collect();
+                    if !self.rooms.contains_key(&code) {
+                        break code;
+                    }
+                };
+                let mut room = Room {
+                    seats: game.names().iter().map(|_| None).collect(),
+                    game,
+                    abandoned_at: None,
+                };
+                let token = room.sit(0, name, client.connection());
+                room.broadcast_seats(&code);
+                self.rooms.insert(code.clone(), room);
+                client.place = Some((code, token));
+            }
+            ClientMessage::Join { code, name } => {
+                let code = code.to_uppercase();
+                self.leave(client);
+                let room = self
+                    .rooms
+                    .get_mut(&code)
+                    .ok_or_else(|| format!("there is no room {}", code))?;
+                let seat = room
+                    .seats
+                    .iter()
+                    .position(Option::is_none)
+                    .ok_or_else(|| format!("room {} is full", code))?;
+                let token = room.sit(seat, name, client.connection());
+                room.broadcast_seats(&code);
+                client.place = Some((code, token));
+            }
+            ClientMessage::Rejoin { code, token } => {
+                let code = code.to_uppercase();
+                self.leave(client);
+                let room = self
+                    .rooms
+                    .get_mut(&code)
+                    .ok_or_else(|| format!("there is no room {}", code))?;
+                let seat = room
+                    .seat_of(&token)
+                    .ok_or_else(|| format!("that seat in room {} is gone", code))?;
+                if let Some(player) = &mut room.seats[seat] {
+                    player.connection = Some(client.connection());
+                }
+                room.abandoned_at = None;
+                room.broadcast_seats(&code);
+                client.place = Some((code, token));
+            }
+            ClientMessage::Connect4Move(_) | ClientMessage::TootOttoMove(..) => {
+                let (room, seat) = self.seated(client)?;
+                room.play(seat, message)?;
+                room.broadcast_state();
+            }
+            ClientMessage::PlayAgain { swap_first } => {
+                let (room, _) = self.seated(client)?;
+                room.play_again(swap_first)?;
+                let code = client
+                    .place
+                    .as_ref()
+                    .map(|(code, _)| code.clone())
+                    .unwrap_or_default();
+                room.broadcast_seats(&code);
+            }
+        }
+        Ok(())
+    }
+
+    /// The room a client sits in, and its seat there.
+    fn seated(&mut self, client: &Client) -> Result<(&mut Room, usize), String> {
+        let (code, token) = client.place.as_ref().ok_or("create or join a room first")?;
+        let room = self
+            .rooms
+            .get_mut(code)
+            .ok_or_else(|| format!("room {} is gone", code))?;
+        let seat = room
+            .seat_of(token)
+            .ok_or_else(|| format!("your seat in room {} is gone", code))?;
+        Ok((room, seat))
+    }
+
+    /// Leaves the room a client was in, when it moves to another or its
+    /// connection closes.
+    fn leave(&mut self, client: &mut Client) {
+        if let Some((code, _)) = client.place.take() {
+            if let Some(room) = self.rooms.get_mut(&code) {
+                room.leave(client.id);
+            }
+        }
+    }
+
+    /// Closes the rooms nobody has come back to for a while.
+    fn prune(&mut self, now: Instant) {
+        self.rooms
+            .retain(|_, room| room.abandoned_at.is_none_or(|at| now - at < ABANDONED_ROOM));
+    }
+}
+
+/// Hosts rooms on `address` until the process is stopped.
+pub fn serve(address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    println!(
+        "Hosting Connect 4 and TOOT-OTTO rooms on ws://{}",
+        listener.local_addr()?
+    );
+    let lobby = Arc::new(Mutex::new(Lobby::default()));
+    for (id, stream) in listener.incoming().enumerate() {
+        match stream {
+            Ok(stream) => {
+                lobby.lock().unwrap().prune(Instant::now());
+                let lobby = Arc::clone(&lobby);
+                thread::spawn(move || handle_connection(stream, id, &lobby));
+            }
+            Err(error) => eprintln!("Can't accept a connection: {}", error),
+        }
+    }
+    Ok(())
+}
+
+/// Talks to one board until its socket closes.
+fn handle_connection(stream: TcpStream, id: usize, lobby: &Mutex<Lobby>) {
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(error) => {
+            eprintln!("Can't open a WebSocket: {}", error);
+            return;
+        }
+    };
+    // Reads give up now and then to let messages from other players out
+    if let Err(error) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        eprintln!("Can't set up a connection: {}", error);
+        return;
+    }
+    let (outbox, inbox) = mpsc::channel();
+    let mut client = Client::new(id, outbox);
+    'connection: loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let mut lobby = lobby.lock().unwrap();
+                let result = text
+                    .parse()
+                    .and_then(|message| lobby.handle(&mut client, message));
+                if let Err(error) = result {
+                    let _ = client.outbox.send(ServerMessage::Error(error).to_string());
+                }
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(error))
+                if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+        for text in inbox.try_iter() {
+            if socket.send(Message::Text(text)).is_err() {
+                break 'connection;
+            }
+        }
+    }
+    lobby.lock().unwrap().leave(&mut client);
+}
+
+fn describe_move(mv: Move) -> String {
+    match mv {
+        Move::Drop(col) => format!("dropping into column {}", col + 1),
+        Move::Pop(col) => format!("popping column {}", col + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::connect4::{Connect4Position, Rules};
+    use crate::engine::record::{Connect4Record, TootOttoRecord};
+    use crate::engine::tootandotto::{TootOttoPosition, TootOttoRules};
+    use std::sync::mpsc::Receiver;
+
+    fn client(id: usize) -> (Client, Receiver<String>) {
+        let (outbox, inbox) = mpsc::channel();
+        (Client::new(id, outbox), inbox)
+    }
+
+    /// The messages a client has been sent since last asked.
+    fn received(inbox: &Receiver<String>) -> Vec<ServerMessage> {
+        inbox.try_iter().map(|text| text.parse().unwrap()).collect()
+    }
+
+    fn connect4_game() -> OnlineGame {
+        let position = Connect4Position::new(Rules::default());
+        let names = vec!["Ann".to_string(), String::new()];
+        OnlineGame::Connect4(Connect4Record::new(&position, names, String::new()))
+    }
+
+    /// Opens a Connect 4 room for a first client and fills it with a
+    /// second, returning the room code.
+    fn full_room(lobby: &mut Lobby, first: &mut Client, second: &mut Client) -> String {
+        lobby
+            .handle(first, ClientMessage::Create(connect4_game()))
+            .unwrap();
+        let (code, _) = first.place.clone().unwrap();
+        let join = ClientMessage::Join {
+            code: code.to_lowercase(),
+            name: "Bo".to_string(),
+        };
+        lobby.handle(second, join).unwrap();
+        code
+    }
+
+    fn drop_disc(lobby: &mut Lobby, client: &mut Client, col: usize) -> Result<(), String> {
+        lobby.handle(client, ClientMessage::Connect4Move(Move::Drop(col)))
+    }
+
+    #[test]
+    fn create_and_join_fill_the_seats_in_order() {
+        let mut lobby = Lobby::default();
+        let (mut ann, ann_inbox) = client(0);
+        let (mut bo, bo_inbox) = client(1);
+        lobby
+            .handle(&mut ann, ClientMessage::Create(connect4_game()))
+            .unwrap();
+        let (code, token) = ann.place.clone().unwrap();
+        assert_eq!(code.len(), CODE_LENGTH);
+        assert_eq!(
+            received(&ann_inbox)[..1],
+            [ServerMessage::Joined {
+                code: code.clone(),
+                player: 1,
+                token,
+            }]
+        );
+
+        let join = ClientMessage::Join {
+            code: code.to_lowercase(),
+            name: " Bo ".to_string(),
+        };
+        lobby.handle(&mut bo, join).unwrap();
+        let messages = received(&bo_inbox);
+        assert!(matches!(
+            &messages[0],
+            ServerMessage::Joined { player: 2, .. }
+        ));
+        match &messages[1] {
+            ServerMessage::State { game, open_seats } => {
+                assert_eq!(game.names(), ["Ann", "Bo"]);
+                assert_eq!(*open_seats, 0);
+            }
+            message => panic!("expected the state, got {:?}", message),
+        }
+        // The first player hears about the second too
+        assert!(received(&ann_inbox).contains(&messages[1]));
+
+        let (mut cy, _) = client(2);
+        let join = ClientMessage::Join {
+            code: code.clone(),
+            name: "Cy".to_string(),
+        };
+        assert_eq!(
+            lobby.handle(&mut cy, join),
+            Err(format!("room {} is full", code))
+        );
+        let join = ClientMessage::Join {
+            code: "NOPE".to_string(),
+            name: "Cy".to_string(),
+        };
+        assert_eq!(
+            lobby.handle(&mut cy, join),
+            Err("there is no room NOPE".to_string())
+        );
+    }
+
+    #[test]
+    fn moves_wait_for_everyone_and_take_turns() {
+        let mut lobby = Lobby::default();
+        let (mut ann, _) = client(0);
+        let (mut bo, bo_inbox) = client(1);
+        assert_eq!(
+            drop_disc(&mut lobby, &mut ann, 0),
+            Err("create or join a room first".to_string())
+        );
+        lobby
+            .handle(&mut ann, ClientMessage::Create(connect4_game()))
+            .unwrap();
+        assert_eq!(
+            drop_disc(&mut lobby, &mut ann, 0),
+            Err("wait for everyone to join first".to_string())
+        );
+        let join = ClientMessage::Join {
+            code: ann.place.clone().unwrap().0,
+            name: "Bo".to_string(),
+        };
+        lobby.handle(&mut bo, join).unwrap();
+        received(&bo_inbox);
+
+        assert_eq!(
+            drop_disc(&mut lobby, &mut bo, 0),
+            Err("it's not your turn".to_string())
+        );
+        drop_disc(&mut lobby, &mut ann, 3).unwrap();
+        match &received(&bo_inbox)[..] {
+            [ServerMessage::State {
+                game: OnlineGame::Connect4(record),
+                ..
+            }] => assert_eq!(record.moves, [Move::Drop(3)]),
+            messages => panic!("expected the state, got {:?}", messages),
+        }
+        assert_eq!(
+            drop_disc(&mut lobby, &mut ann, 3),
+            Err("it's not your turn".to_string())
+        );
+        assert_eq!(
+            lobby.handle(&mut bo, ClientMessage::TootOttoMove(0, 'T')),
+            Err("that's a move for the other game".to_string())
+        );
+        assert_eq!(
+            drop_disc(&mut lobby, &mut bo, 7),
+            Err("dropping into column 8 can't be played".to_string())
+        );
+    }
+
+    #[test]
+    fn again_swap_moves_everyone_up_a_seat_once_the_game_is_over() {
+        let mut lobby = Lobby::default();
+        let (mut ann, _) = client(0);
+        let (mut bo, bo_inbox) = client(1);
+        let code = full_room(&mut lobby, &mut ann, &mut bo);
+        let again = ClientMessage::PlayAgain { swap_first: true };
+        assert_eq!(
+            lobby.handle(&mut ann, again.clone()),
+            Err("the game isn't over yet".to_string())
+        );
+        for _ in 0..3 {
+            drop_disc(&mut lobby, &mut ann, 0).unwrap();
+            drop_disc(&mut lobby, &mut bo, 1).unwrap();
+        }
+        drop_disc(&mut lobby, &mut ann, 0).unwrap();
+        assert_eq!(
+            drop_disc(&mut lobby, &mut bo, 1),
+            Err("the game is over".to_string())
+        );
+        received(&bo_inbox);
+
+        lobby.handle(&mut bo, again).unwrap();
+        let messages = received(&bo_inbox);
+        assert!(matches!(
+            &messages[0],
+            ServerMessage::Joined { player: 1, .. }
+        ));
+        match &messages[1] {
+            ServerMessage::State {
+                game: OnlineGame::Connect4(record),
+                ..
+            } => {
+                assert_eq!(record.names, ["Bo", "Ann"]);
+                assert!(record.moves.is_empty());
+            }
+            message => panic!("expected the state, got {:?}", message),
+        }
+        drop_disc(&mut lobby, &mut bo, 3).unwrap();
+        assert_eq!(lobby.rooms[&code].player_to_move(), Some(2));
+    }
+
+    #[test]
+    fn again_swap_hands_the_first_toot_otto_letter_over() {
+        let mut lobby = Lobby::default();
+        let (mut ann, _) = client(0);
+        let (mut bo, _) = client(1);
+        let position = TootOttoPosition::new(TootOttoRules::for_board(4, 4));
+        let names = vec!["Ann".to_string(), String::new()];
+        let game = OnlineGame::TootOtto(TootOttoRecord::new(&position, names, String::new()));
+        lobby.handle(&mut ann, ClientMessage::Create(game)).unwrap();
+        let join = ClientMessage::Join {
+            code: ann.place.clone().unwrap().0,
+            name: "Bo".to_string(),
+        };
+        lobby.handle(&mut bo, join).unwrap();
+        // Ann spells TOOT across the bottom row
+        for (col, letter) in [(0, 'T'), (1, 'O'), (2, 'O'), (3, 'T')] {
+            lobby
+                .handle(&mut ann, ClientMessage::TootOttoMove(col, letter))
+                .unwrap();
+            if col < 3 {
+                lobby
+                    .handle(&mut bo, ClientMessage::TootOttoMove(col, 'T'))
+                    .unwrap();
+            }
+        }
+        let (code, _) = ann.place.clone().unwrap();
+        assert_eq!(lobby.rooms[&code].player_to_move(), None);
+
+        lobby
+            .handle(&mut ann, ClientMessage::PlayAgain { swap_first: true })
+            .unwrap();
+        assert_eq!(lobby.rooms[&code].player_to_move(), Some(COMPUTER));
+    }
+
+    #[test]
+    fn rejoin_takes_back_the_seat_and_frees_the_old_one() {
+        let mut lobby = Lobby::default();
+        let (mut ann, _) = client(0);
+        let (mut bo, _) = client(1);
+        let code = full_room(&mut lobby, &mut ann, &mut bo);
+        let (_, ann_token) = ann.place.clone().unwrap();
+
+        // Ann's socket drops, and she comes back on a new one
+        lobby.leave(&mut ann);
+        assert!(lobby.rooms[&code].seats[0]
+            .as_ref()
+            .is_some_and(|player| player.connection.is_none()));
+        let (mut ann_again, inbox) = client(2);
+        let rejoin = ClientMessage::Rejoin {
+            code: code.clone(),
+            token: ann_token.clone(),
+        };
+        lobby.handle(&mut ann_again, rejoin).unwrap();
+        assert_eq!(
+            received(&inbox)[0],
+            ServerMessage::Joined {
+                code: code.clone(),
+                player: 1,
+                token: ann_token,
+            }
+        );
+        drop_disc(&mut lobby, &mut ann_again, 3).unwrap();
+
+        // Taking a seat in another room leaves the one in this room
+        let (mut cy, _) = client(3);
+        lobby
+            .handle(&mut cy, ClientMessage::Create(connect4_game()))
+            .unwrap();
+        let (other_code, cy_token) = cy.place.clone().unwrap();
+        lobby.leave(&mut cy);
+        let rejoin = ClientMessage::Rejoin {
+            code: other_code.clone(),
+            token: cy_token,
+        };
+        lobby.handle(&mut bo, rejoin).unwrap();
+        assert!(lobby.rooms[&code].seats[1]
+            .as_ref()
+            .is_some_and(|player| player.connection.is_none()));
+        assert!(lobby.rooms[&other_code].seats[0]
+            .as_ref()
+            .is_some_and(|player| player.connection.is_some()));
+
+        let rejoin = ClientMessage::Rejoin {
+            code: code.clone(),
+            token: "not a token".to_string(),
+        };
+        assert_eq!(
+            lobby.handle(&mut ann_again, rejoin),
+            Err(format!("that seat in room {} is gone", code))
+        );
+    }
+
+    #[test]
+    fn prune_closes_rooms_abandoned_for_long_enough() {
+        let mut lobby = Lobby::default();
+        let (mut ann, _) = client(0);
+        let (mut bo, _) = client(1);
+        let code = full_room(&mut lobby, &mut ann, &mut bo);
+        let (mut cy, _) = client(2);
+        lobby
+            .handle(&mut cy, ClientMessage::Create(connect4_game()))
+            .unwrap();
+
+        lobby.leave(&mut ann);
+        lobby.leave(&mut bo);
+        lobby.prune(Instant::now());
+        assert!(lobby.rooms.contains_key(&code));
+        lobby.prune(Instant::now() + ABANDONED_ROOM);
+        assert!(!lobby.rooms.contains_key(&code));
+        // Somebody is still in the other room
+        assert_eq!(lobby.rooms.len(), 1);
+    }
+}
@@ -0,0 +1,428 @@
+use crate::constant::{COMPUTER, MAX_PLAYERS, USER};
+use crate::engine::connect4::{Connect4Position, Move, Topology, Variant, POP_TEN_TARGET};
+use crate::engine::tootandotto::TootOttoPosition;
+use clap::ValueEnum;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{
+    Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor, Stylize,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, IsTerminal, Stdout, Write};
+
+/// How many lines of move history fit under the board.
+const HISTORY_LINES: usize = 4;
+const HISTORY_WIDTH: usize = 60;
+
+/// The colour themes of the web board, with the same Tailwind colours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Theme {
+    Regular,
+    Colorblind,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+const fn rgb(hex: u32) -> Color {
+    Color::Rgb {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+    }
+}
+
+impl Theme {
+    fn board(&self) -> Color {
+        match self {
+            Theme::Regular => rgb(0x60a5fa),
+            Theme::Colorblind => rgb(0x06b6d4),
+            Theme::Protanopia | Theme::Deuteranopia | Theme::Tritanopia => rgb(0x6b7280),
+        }
+    }
+
+    /// The colour of `player`'s discs and letters.
+    pub fn chip(&self, player: usize) -> Color {
+        let chips: [u32; MAX_PLAYERS] = match self {
+            Theme::Regular => [0xf87171, 0xfacc15, 0x22c55e, 0x9333ea],
+            Theme::Colorblind => [0xea580c, 0x84cc16, 0x5b21b6, 0x111827],
+            Theme::Protanopia => [0xc026d3, 0x06b6d4, 0xfde047, 0x111827],
+            Theme::Deuteranopia => [0xea580c, 0x1d4ed8, 0xfde047, 0x111827],
+            Theme::Tritanopia => [0xef4444, 0x22c55e, 0xf9a8d4, 0x111827],
+        };
+        rgb(chips[player - 1])
+    }
+}
+
+/// The terminal taken over as a full screen for a game with a human in it.
+/// Dropping it gives the terminal back the way it was.
+pub struct Screen {
+    out: Stdout,
+    theme: Theme,
+    /// The column the human is pointing at.
+    cursor: usize,
+    /// TOOT-OTTO: the letter the human is about to place.
+    letter: char,
+}
+
+impl Screen {
+    /// Takes over the terminal, or returns `None` if input or output is not
+    /// a terminal so the game falls back to reading plain lines.
+    pub fn open(theme: Theme) -> Option<Screen> {
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return None;
+        }
+        terminal::enable_raw_mode().ok()?;
+        let mut out = io::stdout();
+        if execute!(out, EnterAlternateScreen, Hide).is_err() {
+            let _ = terminal::disable_raw_mode();
+            return None;
+        }
+        Some(Screen {
+            out,
+            theme,
+            cursor: 0,
+            letter: 'T',
+        })
+    }
+
+    /// Shows the board while nobody is choosing a move, e.g. while the
+    /// computer thinks or once the game is over.
+    pub fn show_connect4(&mut self, board: &Connect4Position, history: &[String], status: &str) {
+        self.draw_connect4(board, history, status, "", false)
+            .expect("Failed to draw the board");
+    }
+
+    /// Lets the human pick a column with the arrow keys. Returns `None` if
+    /// they quit instead.
+    pub fn connect4_move(
+        &mut self,
+        board: &Connect4Position,
+        history: &[String],
+        prompt: &str,
+        message: &str,
+    ) -> Option<Move> {
+        self.cursor = self.cursor.min(board.columns() - 1);
+        let can_pop = matches!(board.variant(), Variant::PopOut | Variant::PopTen);
+        let keys = if can_pop {
+            "←/→ choose a column, Enter drops, P pops, Q quits"
+        } else {
+            "←/→ choose a column, Enter drops, Q quits"
+        };
+        let status = format!("{}: {}", prompt, keys);
+        let mut message = message.to_string();
+        loop {
+            self.draw_connect4(board, history, &status, &message, true)
+                .expect("Failed to draw the board");
+            match self.read_key()? {
+                KeyCode::Left => self.move_cursor(board.columns(), false),
+                KeyCode::Right => self.move_cursor(board.columns(), true),
+                KeyCode::Enter | KeyCode::Down | KeyCode::Char(' ') => {
+                    return Some(Move::Drop(self.cursor))
+                }
+                KeyCode::Char('p') if can_pop => return Some(Move::Pop(self.cursor)),
+                // Columns are numbered from 1, so 0 stands for column 10
+                KeyCode::Char(digit @ '0'..='9') => {
+                    let col = (digit as usize - '0' as usize + 9) % 10;
+                    if col < board.columns() {
+                        self.cursor = col;
+                    }
+                }
+                _ => message = "Use the arrow keys to choose a column.".to_string(),
+            }
+        }
+    }
+
+    pub fn show_toot_otto(
+        &mut self,
+        board: &TootOttoPosition,
+        names: &dyn Fn(usize) -> String,
+        history: &[String],
+        status: &str,
+    ) {
+        self.draw_toot_otto(board, names, history, status, "", false)
+            .expect("Failed to draw the board");
+    }
+
+    /// Lets the human pick a letter and a column. Returns `None` if they
+    /// quit instead.
+    pub fn toot_otto_move(
+        &mut self,
+        board: &TootOttoPosition,
+        names: &dyn Fn(usize) -> String,
+        history: &[String],
+        prompt: &str,
+        message: &str,
+    ) -> Option<(usize, char)> {
+        self.cursor = self.cursor.min(board.columns() - 1);
+        // Don't leave the human holding a letter they have run out of
+        let player = board.current_player();
+        if board.letters_left(player, self.letter) == 0 {
+            self.letter = if self.letter == 'T' { 'O' } else { 'T' };
+        }
+        let mut message = message.to_string();
+        loop {
+            let status = format!(
+                "{} with {}: ←/→ choose a column, T/O switch letter, Enter places, Q quits",
+                prompt, self.letter
+            );
+            self.draw_toot_otto(board, names, history, &status, &message, true)
+                .expect("Failed to draw the board");
+            match self.read_key()? {
+                KeyCode::Left => self.move_cursor(board.columns(), false),
+                KeyCode::Right => self.move_cursor(board.columns(), true),
+                KeyCode::Tab => self.letter = if self.letter == 'T' { 'O' } else { 'T' },
+                KeyCode::Char('t') => self.letter = 'T',
+                KeyCode::Char('o') => self.letter = 'O',
+                KeyCode::Enter | KeyCode::Down | KeyCode::Char(' ') => {
+                    return Some((self.cursor, self.letter))
+                }
+                _ => message = "Use the arrow keys to choose a column.".to_string(),
+            }
+        }
+    }
+
+    /// Waits for any key, so the final board stays up until it has been seen.
+    pub fn wait_for_key(&mut self) {
+        let _ = self.read_key();
+    }
+
+    /// The next key pressed, or `None` for Q, Esc or Ctrl-C.
+    fn read_key(&mut self) -> Option<KeyCode> {
+        loop {
+            match event::read().expect("Failed to read a key") {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    return match key.code {
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => None,
+                        KeyCode::Char('q') | KeyCode::Esc => None,
+                        KeyCode::Char(c) => Some(KeyCode::Char(c.to_ascii_lowercase())),
+                        code => Some(code),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn move_cursor(&mut self, columns: usize, right: bool) {
+        self.cursor = if right {
+            (self.cursor + 1) % columns
+        } else {
+            (self.cursor + columns - 1) % columns
+        };
+    }
+
+    fn draw_connect4(
+        &mut self,
+        board: &Connect4Position,
+        history: &[String],
+        status: &str,
+        message: &str,
+        show_cursor: bool,
+    ) -> io::Result<()> {
+        queue!(self.out, MoveTo(0, 0), Clear(ClearType::All))?;
+        let title = format!(
+            "Connect {} - {}, {}x{}{}",
+            board.win_length(),
+            board.variant().name(),
+            board.rows(),
+            board.columns(),
+            if board.topology() == Topology::Cylinder {
+                ", cylinder"
+            } else {
+                ""
+            }
+        );
+        self.line(&title.bold().to_string())?;
+        self.blank()?;
+
+        // The opposite edges are repeated, dimmed, so wrapped lines show up
+        let wraps = board.topology() == Topology::Cylinder;
+        let edge = if wraps { 3 } else { 0 };
+        let player = board.current_player();
+        if show_cursor {
+            let marker = " ▼ ".with(self.theme.chip(player)).bold();
+            self.line(&format!(
+                "{}{}",
+                " ".repeat(1 + edge + self.cursor * 3),
+                marker
+            ))?;
+        } else {
+            self.blank()?;
+        }
+        let last = board.last_move();
+        for row in 0..board.rows() {
+            queue!(self.out, Print(" "))?;
+            if wraps {
+                self.disc(board, row, board.columns() - 1, false, true)?;
+            }
+            for col in 0..board.columns() {
+                let is_last = last == Some((col, row));
+                self.disc(board, row, col, is_last, false)?;
+            }
+            if wraps {
+                self.disc(board, row, 0, false, true)?;
+            }
+            self.blank()?;
+        }
+        let numbers: String = (1..=board.columns())
+            .map(|col| format!("{:^3}", col))
+            .collect();
+        self.line(&format!(" {}{}", " ".repeat(edge), numbers))?;
+        self.blank()?;
+
+        if board.variant() == Variant::PopTen {
+            let collected: Vec<String> = (1..=board.players())
+                .map(|player| {
+                    format!(
+                        "{} {}",
+                        "●".with(self.theme.chip(player)),
+                        board.collected(player)
+                    )
+                })
+                .collect();
+            self.line(&format!(
+                "Collected: {} (first to {})",
+                collected.join("  "),
+                POP_TEN_TARGET
+            ))?;
+        }
+        self.footer(history, status, message)
+    }
+
+    fn disc(
+        &mut self,
+        board: &Connect4Position,
+        row: usize,
+        col: usize,
+        is_last: bool,
+        is_ghost: bool,
+    ) -> io::Result<()> {
+        let background = if is_last {
+            Color::White
+        } else {
+            self.theme.board()
+        };
+        queue!(self.out, SetBackgroundColor(background))?;
+        if is_ghost {
+            queue!(self.out, SetAttribute(Attribute::Dim))?;
+        }
+        match board.cell(row, col) {
+            0 => queue!(self.out, SetForegroundColor(Color::White), Print(" · "))?,
+            player => queue!(
+                self.out,
+                SetForegroundColor(self.theme.chip(player)),
+                Print(" ● ")
+            )?,
+        }
+        queue!(self.out, SetAttribute(Attribute::Reset))
+    }
+
+    fn draw_toot_otto(
+        &mut self,
+        board: &TootOttoPosition,
+        names: &dyn Fn(usize) -> String,
+        history: &[String],
+        status: &str,
+        message: &str,
+        show_cursor: bool,
+    ) -> io::Result<()> {
+        queue!(self.out, MoveTo(0, 0), Clear(ClearType::All))?;
+        let title = format!("TOOT-OTTO - {}x{}", board.rows(), board.columns());
+        self.line(&title.bold().to_string())?;
+        self.blank()?;
+
+        let player = board.current_player();
+        if show_cursor {
+            let marker = format!(" {} ", self.letter)
+                .with(self.theme.chip(player))
+                .bold();
+            self.line(&format!("{}{}", " ".repeat(1 + self.cursor * 3), marker))?;
+        } else {
+            self.blank()?;
+        }
+        let last = board.last_move();
+        for row in 0..board.rows() {
+            queue!(self.out, Print(" "))?;
+            for col in 0..board.columns() {
+                let background = if last == Some((col, row)) {
+                    Color::White
+                } else {
+                    self.theme.board()
+                };
+                queue!(self.out, SetBackgroundColor(background))?;
+                match board.cell(row, col) {
+                    (' ', _) => queue!(self.out, SetForegroundColor(Color::White), Print(" · "))?,
+                    (letter, owner) => queue!(
+                        self.out,
+                        SetForegroundColor(self.theme.chip(owner)),
+                        SetAttribute(Attribute::Bold),
+                        Print(format!(" {} ", letter))
+                    )?,
+                }
+                queue!(self.out, SetAttribute(Attribute::Reset))?;
+            }
+            self.blank()?;
+        }
+        let numbers: String = (1..=board.columns())
+            .map(|col| format!("{:^3}", col))
+            .collect();
+        self.line(&format!(" {}", numbers))?;
+        self.blank()?;
+
+        for player in [USER, COMPUTER] {
+            let line = format!(
+                "{} ({}): {} T's and {} O's left",
+                names(player).with(self.theme.chip(player)).bold(),
+                board.word(player).name(),
+                board.letters_left(player, 'T'),
+                board.letters_left(player, 'O')
+            );
+            self.line(&line)?;
+        }
+        self.footer(history, status, message)
+    }
+
+    /// The move history, the status line and any complaint about the last
+    /// key or move, under the board.
+    fn footer(&mut self, history: &[String], status: &str, message: &str) -> io::Result<()> {
+        self.blank()?;
+        let mut lines: Vec<String> = Vec::new();
+        for entry in history {
+            match lines.last_mut() {
+                Some(line) if line.len() + entry.len() < HISTORY_WIDTH => {
+                    line.push_str("  ");
+                    line.push_str(entry);
+                }
+                _ => lines.push(entry.clone()),
+            }
+        }
+        self.line(&"Moves:".bold().to_string())?;
+        let skipped = lines.len().saturating_sub(HISTORY_LINES);
+        for line in &lines[skipped..] {
+            self.line(line)?;
+        }
+        self.blank()?;
+        self.line(status)?;
+        if !message.is_empty() {
+            self.line(&message.red().to_string())?;
+        }
+        self.out.flush()
+    }
+
+    fn line(&mut self, text: &str) -> io::Result<()> {
+        queue!(self.out, Print(text), Print("\r\n"))
+    }
+
+    fn blank(&mut self) -> io::Result<()> {
+        queue!(self.out, Print("\r\n"))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -3,5 +3,6 @@ pub mod burgermenu;
 pub mod connect4board;
 pub mod connect4rules;
 pub mod home;
+pub mod online;
 pub mod tootandottoboard;
 pub mod winner_modal;
@@ -1,129 +1,284 @@
+use crate::components::online::{Connection, OnlineEvent};
 use crate::components::winner_modal::WinnerModal;
-use crate::constant::{COMPUTER, EMPTY, USER}; //,columns, rows, EMPTY, USER};
+use crate::constant::{
+    CHIP_COLORS, DEFAULT_C4_WIN_LENGTH, DEFAULT_SERVER_PORT, EMPTY, HARD_TIME_BUDGET_MS,
+    MAX_PLAYERS, PERFECT_WEB_TIME_BUDGET_MS,
+}; //,columns, rows, EMPTY, USER};
+use crate::engine::clock::today;
+use crate::engine::connect4::{
+    search, solve, Connect4Position, Move, Rules, Seat, Solution, Topology, Variant, MAX_COLUMNS,
+    MAX_ROWS, MIN_WIN_LENGTH, POP_TEN_TARGET,
+};
+use crate::engine::online::{ClientMessage, OnlineGame, ServerMessage};
+use crate::engine::record::Connect4Record;
 use gloo_console::log;
 use gloo_timers::callback::Timeout;
 use rand::prelude::*;
-use web_sys::HtmlInputElement;
+use web_sys::js_sys::encode_uri_component;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
 use yew::prelude::*;
 
-use std::cmp::{max, min};
-
 #[function_component]
 pub fn Connect4Board() -> Html {
 
-    let columns = use_state(|| 7);
-    let rows = use_state(|| 6);
     let input_columns = use_state(|| 7); // Holds the input field value for columns
     let input_rows = use_state(|| 6); // Holds the input field value for rows
+    let input_win_length = use_state(|| DEFAULT_C4_WIN_LENGTH); // Holds the input field value for the win length
+    let input_variant = use_state(|| Variant::Standard);
+    let input_topology = use_state(|| Topology::Flat);
+    let input_players = use_state(|| 2);
+    let input_seats = use_state(|| {
+        let mut seats = vec![Seat::Computer; MAX_PLAYERS];
+        seats[0] = Seat::Human;
+        seats
+    });
+    // What the humans in each seat want to be called, blank for "Player N"
+    let input_names = use_state(|| vec![String::new(); MAX_PLAYERS]);
+    // "User", "Computer", "Random" or "Alternate", who moves first each game
+    let input_first = use_state(|| "User".to_string());
+    // Whether a game has been started here yet, as "Alternate" lets the user
+    // open the first one
+    let started = use_state(|| false);
+    // Whether to play people on other machines through the game server, and
+    // the room to join there, blank to open a new one
+    let input_online = use_state(|| false);
+    let input_server = use_state(|| format!("ws://localhost:{}", DEFAULT_SERVER_PORT));
+    let input_room = use_state(String::new);
 
-    let board = use_state(|| vec![vec![0; *columns]; *rows]);
+    let board = use_state(|| Connect4Position::new(Rules::default()));
+    let seats = use_state(|| vec![Seat::Human, Seat::Computer]);
+    let names = use_state(|| vec![String::new(); 2]);
     let winner = use_state(|| None::<usize>);
     let difficulty = use_state(|| "Easy".to_string());
-    let last_move = use_state(|| None::<(usize, usize)>);
     let is_user_turn = use_state(|| true);
-
-    let make_computer_move =
-        |board: &mut Vec<Vec<usize>>, difficulty: &String| -> Option<(usize, usize)> {
-            let rows = board.len();
-            let columns = board.get(0).map_or(0, |row| row.len());
-
-            if *difficulty == "Easy" {
-                let available_cols: Vec<usize> = (0..columns)
-                    .filter(|&col| board[0][col] == EMPTY)
-                    .collect();
-                if let Some(&rand_col) = available_cols.choose(&mut rand::thread_rng()) {
-                    if let Some(row) = (0..rows)
-                        .rev()
-                        .find(|&r| board[r][rand_col] == EMPTY)
-                    {
-                        log!("Computer picked column:", rand_col);
-                        board[row][rand_col] = COMPUTER;
-                        return Some((rand_col, row));
-                    }
-                }
-            } else {
-                let (best_col, _) = minimax(board, 5, isize::MIN, isize::MAX, true);
-                if let Some(row) = get_next_open_row(board, best_col) {
-                    log!("Computer picked column:", best_col);
-                    board[row][best_col] = COMPUTER;
-                    return Some((best_col, row));
-                }
-            }
-            None
-        };
+    let analysis = use_state(|| None::<String>);
+    // Moves taken back with Undo, the next one to play again last
+    let redo = use_state(Vec::<Move>::new);
+    // The game record being exported or pasted in, and why it couldn't be
+    // loaded
+    let record_text = use_state(String::new);
+    let record_error = use_state(|| None::<String>);
+    // The room on the game server, this board's code and player number
+    // there, and how the connection is doing
+    let online = use_state(|| None::<Connection>);
+    let online_seat = use_state(|| None::<(String, usize)>);
+    let online_status = use_state(|| None::<String>);
+    // The computer's move waiting to be played. Dropping it cancels the move,
+    // which every new game does so it can't land on the fresh board.
+    let computer_turn = use_mut_ref(|| None::<Timeout>);
 
     let handle_user_move = {
         let board = board.clone();
-        let rows = rows.clone();
+        let seats = seats.clone();
         let winner = winner.clone();
         let difficulty = difficulty.clone();
-        let last_move = last_move.clone();
         let is_user_turn = is_user_turn.clone();
+        let analysis = analysis.clone();
+        let redo = redo.clone();
+        let online = online.clone();
+        let online_seat = online_seat.clone();
+        let computer_turn = computer_turn.clone();
 
-        Callback::from(move |col: usize| {
-            if !*is_user_turn {
+        Callback::from(move |mv: Move| {
+            if !*is_user_turn || !is_local_turn(&board, &seats, &online, &online_seat) {
+                return;
+            }
+            // The server plays the move and sends everyone the new board
+            if let Some(connection) = &*online {
+                connection.send(&ClientMessage::Connect4Move(mv));
                 return;
             }
             let mut new_board = (*board).clone();
-            if let Some(row) = (0..*rows)
-                .rev()
-                .find(|&row| new_board[row][col] == EMPTY)
-            {
-                log!("User picked column:", col);
+            if new_board.play(mv) {
+                // A new move makes the ones taken back meaningless
+                redo.set(Vec::new());
+                log!(format!("Player {} played:", board.current_player()), format!("{:?}", mv));
 
-                new_board[row][col] = USER;
                 board.set(new_board.clone());
-                last_move.set(Some((col, row)));
-                is_user_turn.set(false);
-
-                if let Some(winner_player) = check_winner(&new_board) {
-                    winner.set(Some(winner_player));
-                } else {
-                    let new_board = new_board.clone();
-                    let difficulty = difficulty.clone();
-                    let board = board.clone();
-                    let winner = winner.clone();
-                    let last_move = last_move.clone();
-                    let is_user_turn = is_user_turn.clone();
-                    let timeout = Timeout::new(500, move || {
-                        let mut new_board = new_board;
-                        if let Some((col, row)) = make_computer_move(&mut new_board, &difficulty) {
-                            last_move.set(Some((col, row)));
-                        }
-                        if let Some(winner_player) = check_winner(&new_board) {
-                            winner.set(Some(winner_player));
-                        } else if check_draw(&new_board) {
-                            winner.set(Some(EMPTY));
-                        }
-                        board.set(new_board);
-                        is_user_turn.set(true);
-                    });
-                    timeout.forget();
-                }
+                *computer_turn.borrow_mut() = play_computer_turns(
+                    new_board,
+                    (*seats).clone(),
+                    (*difficulty).clone(),
+                    board.clone(),
+                    winner.clone(),
+                    is_user_turn.clone(),
+                    analysis.clone(),
+                );
             }
         })
     };
 
     let handle_difficulty_change = {
         let difficulty = difficulty.clone();
+        let analysis = analysis.clone();
         Callback::from(move |e: Event| {
             let input: HtmlInputElement = e.target_unchecked_into();
             difficulty.set(input.value());
+            analysis.set(None);
+        })
+    };
+
+    // Keeps the board in step with the room on the game server
+    let on_online_event = {
+        let board = board.clone();
+        let seats = seats.clone();
+        let names = names.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let online_seat = online_seat.clone();
+        let online_status = online_status.clone();
+        Callback::from(move |event: OnlineEvent| match event {
+            OnlineEvent::Message(ServerMessage::Joined { code, player, .. }) => {
+                online_seat.set(Some((code, player)));
+            }
+            OnlineEvent::Message(ServerMessage::State {
+                game: OnlineGame::Connect4(record),
+                open_seats,
+            }) => {
+                let Ok(new_board) = record.position() else {
+                    return;
+                };
+                winner.set(if let Some(winner_player) = new_board.winner() {
+                    Some(winner_player)
+                } else if new_board.is_draw() {
+                    Some(EMPTY)
+                } else {
+                    None
+                });
+                board.set(new_board);
+                seats.set(vec![Seat::Human; record.rules.players]);
+                names.set(record.names);
+                is_user_turn.set(true);
+                online_status.set(match open_seats {
+                    0 => None,
+                    1 => Some("Waiting for 1 more player to join".to_string()),
+                    _ => Some(format!("Waiting for {} more players to join", open_seats)),
+                });
+            }
+            OnlineEvent::Message(ServerMessage::State { .. }) => {
+                online_status.set(Some("That room is playing TOOT-OTTO".to_string()));
+            }
+            OnlineEvent::Message(ServerMessage::Error(error)) => {
+                online_status.set(Some(format!("Can't do that: {}", error)));
+            }
+            OnlineEvent::Reconnecting => {
+                online_status.set(Some("Lost the connection, reconnecting...".to_string()));
+            }
+            OnlineEvent::Disconnected => {
+                online_status.set(Some("Can't reach the game server, start a new game to try again".to_string()));
+            }
         })
     };
 
       // Updates the state when the form is submitted, not when the inputs change
       let on_submit = {
         let board = board.clone();
-        let rows = rows.clone();
-        let columns = columns.clone();
         let input_rows = input_rows.clone();
         let input_columns = input_columns.clone();
+        let input_win_length = input_win_length.clone();
+        let input_variant = input_variant.clone();
+        let input_topology = input_topology.clone();
+        let input_players = input_players.clone();
+        let input_seats = input_seats.clone();
+        let input_names = input_names.clone();
+        let input_first = input_first.clone();
+        let started = started.clone();
+        let input_online = input_online.clone();
+        let input_server = input_server.clone();
+        let input_room = input_room.clone();
+        let seats = seats.clone();
+        let names = names.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let difficulty = difficulty.clone();
+        let analysis = analysis.clone();
+        let redo = redo.clone();
+        let online = online.clone();
+        let online_seat = online_seat.clone();
+        let online_status = online_status.clone();
+        let computer_turn = computer_turn.clone();
         Callback::from(move |e: SubmitEvent| {
             e.prevent_default();
-            rows.set(*input_rows);
-            columns.set(*input_columns);
-            board.set(vec![vec![0; *input_columns]; *input_rows]);
+            let rows = (*input_rows).clamp(4, MAX_ROWS);
+            let columns = (*input_columns).clamp(4, MAX_COLUMNS);
+            // A line can't be longer than the board
+            let win_length = (*input_win_length).clamp(MIN_WIN_LENGTH, rows.max(columns));
+            // Some variants come with a board of their own
+            let rules = input_variant.fixed_rules().unwrap_or(Rules {
+                rows,
+                columns,
+                win_length,
+                variant: *input_variant,
+                topology: *input_topology,
+                players: (*input_players).clamp(2, MAX_PLAYERS),
+            });
+            let new_board = Connect4Position::new(rules);
+            // The solver only knows the standard board
+            let mut new_difficulty = (*difficulty).clone();
+            if new_difficulty == "Perfect" && !is_standard_board(&new_board) {
+                new_difficulty = "Hard".to_string();
+                difficulty.set(new_difficulty.clone());
+            }
+            input_rows.set(rules.rows);
+            input_columns.set(rules.columns);
+            input_win_length.set(rules.win_length);
+            input_topology.set(rules.topology);
+            input_players.set(rules.players);
+            winner.set(None);
+            analysis.set(None);
+            redo.set(Vec::new());
+            computer_turn.borrow_mut().take();
+            // A new game leaves the room this board was in
+            if let Some(connection) = &*online {
+                connection.close();
+            }
+            online_seat.set(None);
+            if *input_online {
+                let opening = if input_room.trim().is_empty() {
+                    let record = Connect4Record::new(&new_board, input_names[..rules.players].to_vec(), today());
+                    ClientMessage::Create(OnlineGame::Connect4(record))
+                } else {
+                    ClientMessage::Join {
+                        code: input_room.trim().to_string(),
+                        name: input_names[0].clone(),
+                    }
+                };
+                // The server sends the board and the players once it has
+                // found the room
+                // Set first, as a bad address is reported while opening
+                online_status.set(Some("Connecting to the game server...".to_string()));
+                online.set(Some(Connection::open(&input_server, opening, on_online_event.clone())));
+                board.set(new_board);
+                seats.set(vec![Seat::Human; rules.players]);
+                names.set(vec![String::new(); rules.players]);
+                is_user_turn.set(true);
+                return;
+            }
+            online.set(None);
+            online_status.set(None);
+            let mut new_seats = input_seats[..rules.players].to_vec();
+            let mut new_names = input_names[..rules.players].to_vec();
+            seat_first(&mut new_seats, &mut new_names, &input_first, started.then_some(seats[0]));
+            started.set(true);
+            let mut new_input_seats = (*input_seats).clone();
+            new_input_seats[..rules.players].copy_from_slice(&new_seats);
+            let mut new_input_names = (*input_names).clone();
+            new_input_names[..rules.players].clone_from_slice(&new_names);
+            board.set(new_board.clone());
+            seats.set(new_seats.clone());
+            names.set(new_names);
+            input_seats.set(new_input_seats);
+            input_names.set(new_input_names);
+            // Player 1 may well be a computer
+            *computer_turn.borrow_mut() = play_computer_turns(
+                new_board,
+                new_seats,
+                new_difficulty,
+                board.clone(),
+                winner.clone(),
+                is_user_turn.clone(),
+                analysis.clone(),
+            );
         })
     };
 
@@ -146,6 +301,305 @@ pub fn Connect4Board() -> Html {
         })
     };
 
+    let on_variant_change = {
+        let input_variant = input_variant.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_variant.set(match input.value().as_str() {
+                "PopOut" => Variant::PopOut,
+                "FiveInARow" => Variant::FiveInARow,
+                "PopTen" => Variant::PopTen,
+                _ => Variant::Standard,
+            });
+        })
+    };
+
+    let on_topology_change = {
+        let input_topology = input_topology.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_topology.set(if input.checked() {
+                Topology::Cylinder
+            } else {
+                Topology::Flat
+            });
+        })
+    };
+
+    let on_players_change = {
+        let input_players = input_players.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+                input_players.set(input.value_as_number() as usize);
+            }
+        })
+    };
+
+    let on_seat_change = {
+        let input_seats = input_seats.clone();
+        Callback::from(move |(seat, e): (usize, Event)| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut new_seats = (*input_seats).clone();
+            new_seats[seat] = if input.checked() {
+                Seat::Computer
+            } else {
+                Seat::Human
+            };
+            input_seats.set(new_seats);
+        })
+    };
+
+    // Fills every seat with a computer after player 1, or with people taking
+    // turns on this device. Online, the other seats go to whoever joins.
+    let on_opponent_change = {
+        let input_seats = input_seats.clone();
+        let input_online = input_online.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut new_seats = vec![Seat::Human; MAX_PLAYERS];
+            if input.value() == "Computer" {
+                new_seats[1..].fill(Seat::Computer);
+            }
+            input_seats.set(new_seats);
+            input_online.set(input.value() == "Online");
+        })
+    };
+
+    let on_server_change = {
+        let input_server = input_server.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_server.set(input.value());
+        })
+    };
+
+    let on_room_change = {
+        let input_room = input_room.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_room.set(input.value());
+        })
+    };
+
+    let on_name_change = {
+        let input_names = input_names.clone();
+        Callback::from(move |(seat, e): (usize, InputEvent)| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut new_names = (*input_names).clone();
+            new_names[seat] = input.value();
+            input_names.set(new_names);
+        })
+    };
+
+    // A new game on the same board, with the same players and difficulty
+    let on_play_again = {
+        let board = board.clone();
+        let input_seats = input_seats.clone();
+        let input_names = input_names.clone();
+        let input_first = input_first.clone();
+        let seats = seats.clone();
+        let names = names.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let difficulty = difficulty.clone();
+        let analysis = analysis.clone();
+        let redo = redo.clone();
+        let online = online.clone();
+        let computer_turn = computer_turn.clone();
+        Callback::from(move |swap_first: bool| {
+            // The room starts over for everyone, and sends the new seats
+            if let Some(connection) = &*online {
+                connection.send(&ClientMessage::PlayAgain { swap_first });
+                return;
+            }
+            let new_board = Connect4Position::new(board.rules());
+            let mut new_seats = (*seats).clone();
+            let mut new_names = (*names).clone();
+            // Everyone moves up a seat, so the first player goes last
+            if swap_first {
+                new_seats.rotate_left(1);
+                new_names.rotate_left(1);
+            } else {
+                seat_first(&mut new_seats, &mut new_names, &input_first, Some(seats[0]));
+            }
+            let mut new_input_seats = (*input_seats).clone();
+            new_input_seats[..new_seats.len()].copy_from_slice(&new_seats);
+            let mut new_input_names = (*input_names).clone();
+            new_input_names[..new_names.len()].clone_from_slice(&new_names);
+            board.set(new_board.clone());
+            seats.set(new_seats.clone());
+            names.set(new_names);
+            input_seats.set(new_input_seats);
+            input_names.set(new_input_names);
+            winner.set(None);
+            analysis.set(None);
+            redo.set(Vec::new());
+            *computer_turn.borrow_mut() = play_computer_turns(
+                new_board,
+                new_seats,
+                (*difficulty).clone(),
+                board.clone(),
+                winner.clone(),
+                is_user_turn.clone(),
+                analysis.clone(),
+            );
+        })
+    };
+
+    // Takes back the last human move along with the computers' replies
+    let on_undo = {
+        let board = board.clone();
+        let seats = seats.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let analysis = analysis.clone();
+        let redo = redo.clone();
+        Callback::from(move |_: MouseEvent| {
+            if !*is_user_turn {
+                return;
+            }
+            if let Some((new_board, undone)) = take_back(&board, &seats) {
+                let mut new_redo = (*redo).clone();
+                new_redo.extend(undone);
+                board.set(new_board);
+                redo.set(new_redo);
+                winner.set(None);
+                analysis.set(None);
+            }
+        })
+    };
+
+    // Plays the last move taken back again, and the computers' replies to it
+    let on_redo = {
+        let board = board.clone();
+        let seats = seats.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let redo = redo.clone();
+        Callback::from(move |_: MouseEvent| {
+            if !*is_user_turn {
+                return;
+            }
+            let mut new_board = (*board).clone();
+            let mut new_redo = (*redo).clone();
+            while let Some(mv) = new_redo.pop() {
+                new_board.play(mv);
+                if new_redo.is_empty() || seats[new_board.current_player() - 1] == Seat::Human {
+                    break;
+                }
+            }
+            if let Some(winner_player) = new_board.winner() {
+                winner.set(Some(winner_player));
+            } else if new_board.is_draw() {
+                winner.set(Some(EMPTY));
+            }
+            board.set(new_board);
+            redo.set(new_redo);
+        })
+    };
+
+    let on_export = {
+        let board = board.clone();
+        let seats = seats.clone();
+        let names = names.clone();
+        let record_text = record_text.clone();
+        let record_error = record_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            record_text.set(game_record(&board, &seats, &names).to_string());
+            record_error.set(None);
+        })
+    };
+
+    let on_record_change = {
+        let record_text = record_text.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            record_text.set(input.value());
+        })
+    };
+
+    // Starts over from the pasted record, keeping who sits in each seat
+    let on_import = {
+        let board = board.clone();
+        let input_rows = input_rows.clone();
+        let input_columns = input_columns.clone();
+        let input_win_length = input_win_length.clone();
+        let input_variant = input_variant.clone();
+        let input_topology = input_topology.clone();
+        let input_players = input_players.clone();
+        let input_seats = input_seats.clone();
+        let input_names = input_names.clone();
+        let seats = seats.clone();
+        let names = names.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let difficulty = difficulty.clone();
+        let analysis = analysis.clone();
+        let record_text = record_text.clone();
+        let record_error = record_error.clone();
+        let redo = redo.clone();
+        let computer_turn = computer_turn.clone();
+        Callback::from(move |_: MouseEvent| {
+            let new_board = match record_text
+                .parse::<Connect4Record>()
+                .and_then(|record| record.position())
+            {
+                Ok(new_board) => new_board,
+                Err(error) => {
+                    record_error.set(Some(format!("Can't load the game: {}", error)));
+                    return;
+                }
+            };
+            let rules = new_board.rules();
+            // The solver only knows the standard board
+            let mut new_difficulty = (*difficulty).clone();
+            if new_difficulty == "Perfect" && !is_standard_board(&new_board) {
+                new_difficulty = "Hard".to_string();
+                difficulty.set(new_difficulty.clone());
+            }
+            let new_seats = input_seats[..rules.players].to_vec();
+            board.set(new_board.clone());
+            seats.set(new_seats.clone());
+            names.set(input_names[..rules.players].to_vec());
+            input_rows.set(rules.rows);
+            input_columns.set(rules.columns);
+            input_win_length.set(rules.win_length);
+            input_variant.set(rules.variant);
+            input_topology.set(rules.topology);
+            input_players.set(rules.players);
+            winner.set(None);
+            analysis.set(None);
+            record_error.set(None);
+            redo.set(Vec::new());
+            *computer_turn.borrow_mut() = play_computer_turns(
+                new_board,
+                new_seats,
+                new_difficulty,
+                board.clone(),
+                winner.clone(),
+                is_user_turn.clone(),
+                analysis.clone(),
+            );
+        })
+    };
+
+    let on_first_change = {
+        let input_first = input_first.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_first.set(input.value());
+        })
+    };
+
+    let on_win_length_change = {
+        let input_win_length = input_win_length.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+                input_win_length.set(input.value_as_number() as usize);
+            }
+        })
+    };
+
     html! {
         <>
             <form onsubmit={on_submit}>
@@ -157,12 +611,150 @@ pub fn Connect4Board() -> Html {
                     <label for="cols_input">{"Columns:"}</label>
                     <input id="cols_input" type="number" min="4" max="10" value={(*input_columns).to_string()} oninput={on_cols_change} />
                 </div>
+                <div>
+                    <label for="win_length_input">{"Discs in a row to win:"}</label>
+                    <input id="win_length_input" type="number" min="3" max="10" value={(*input_win_length).to_string()} oninput={on_win_length_change} />
+                </div>
+                <div>
+                    <input type="radio" id="variant_standard" name="variant" value="Standard"
+                        checked={*input_variant == Variant::Standard}
+                        onchange={on_variant_change.clone()}/>
+                    <label for="variant_standard">{"Standard rules"}</label>
+
+                    <input type="radio" id="variant_popout" name="variant" value="PopOut"
+                        checked={*input_variant == Variant::PopOut}
+                        onchange={on_variant_change.clone()}/>
+                    <label for="variant_popout">{"PopOut (pop your own disc out of the bottom row instead of dropping)"}</label>
+
+                    <input type="radio" id="variant_five_in_a_row" name="variant" value="FiveInARow"
+                        checked={*input_variant == Variant::FiveInARow}
+                        onchange={on_variant_change.clone()}/>
+                    <label for="variant_five_in_a_row">{"Five-in-a-Row (9x6, outer columns start filled)"}</label>
+
+                    <input type="radio" id="variant_pop_ten" name="variant" value="PopTen"
+                        checked={*input_variant == Variant::PopTen}
+                        onchange={on_variant_change}/>
+                    <label for="variant_pop_ten">{"Pop 10 (fill the board, then pop out lines of four to collect ten discs)"}</label>
+                </div>
+                <div>
+                    <input type="checkbox" id="topology_cylinder"
+                        checked={*input_topology == Topology::Cylinder}
+                        disabled={input_variant.fixed_rules().is_some()}
+                        onchange={on_topology_change}/>
+                    <label for="topology_cylinder">{"Cylinder (lines carry on across the left and right edges)"}</label>
+                </div>
+                <div>
+                    {"Opponent: "}
+                    <input type="radio" id="opponent_computer" name="opponent" value="Computer"
+                        checked={!*input_online && input_seats[1..(*input_players).clamp(2, MAX_PLAYERS)].contains(&Seat::Computer)}
+                        onchange={on_opponent_change.clone()}/>
+                    <label for="opponent_computer">{"Computer"}</label>
+
+                    <input type="radio" id="opponent_human" name="opponent" value="Human"
+                        checked={!*input_online && !input_seats[1..(*input_players).clamp(2, MAX_PLAYERS)].contains(&Seat::Computer)}
+                        onchange={on_opponent_change.clone()}/>
+                    <label for="opponent_human">{"Human (same device)"}</label>
+
+                    <input type="radio" id="opponent_online" name="opponent" value="Online"
+                        checked={*input_online}
+                        onchange={on_opponent_change}/>
+                    <label for="opponent_online">{"Online"}</label>
+                </div>
+                { if *input_online {
+                    html! {
+                        <div>
+                            <label for="server_input">{"Game server:"}</label>
+                            <input id="server_input" type="text" value={(*input_server).clone()} oninput={on_server_change} />
+                            <label for="room_input">{"Room code:"}</label>
+                            <input id="room_input" type="text" placeholder="Blank for a new room"
+                                value={(*input_room).clone()} oninput={on_room_change} />
+                            <label for="online_name_input">{"Your name:"}</label>
+                            <input id="online_name_input" type="text" placeholder="Player"
+                                value={input_names[0].clone()}
+                                oninput={on_name_change.reform(|e: InputEvent| (0, e))} />
+                        </div>
+                    }
+                } else {
+                    html! {}
+                }}
+                <div>
+                    <label for="players_input">{"Players:"}</label>
+                    <input id="players_input" type="number" min="2" max="4" value={(*input_players).to_string()}
+                        disabled={input_variant.fixed_rules().is_some()}
+                        oninput={on_players_change} />
+                    // Online, the other seats go to whoever joins the room
+                    { for (0..(*input_players).clamp(2, MAX_PLAYERS)).filter(|_| !*input_online).map(|seat| html! {
+                        <span>
+                            <input type="checkbox" id={format!("seat_{}_computer", seat)}
+                                checked={input_seats[seat] == Seat::Computer}
+                                onchange={on_seat_change.reform(move |e: Event| (seat, e))}/>
+                            <label for={format!("seat_{}_computer", seat)}>{format!("Player {} is a computer", seat + 1)}</label>
+                            { if input_seats[seat] == Seat::Human {
+                                html! {
+                                    <input type="text" placeholder={format!("Player {}", seat + 1)}
+                                        value={input_names[seat].clone()}
+                                        oninput={on_name_change.reform(move |e: InputEvent| (seat, e))} />
+                                }
+                            } else {
+                                html! {}
+                            }}
+                        </span>
+                    })}
+                </div>
+                <div>
+                    {"First move: "}
+                    { for first_choices(&input_seats[..(*input_players).clamp(2, MAX_PLAYERS)]).into_iter().map(|(first, label)| html! {
+                        <>
+                            <input type="radio" id={format!("first_{}", first)} name="first" value={first}
+                                checked={*input_first == first}
+                                onchange={on_first_change.clone()}/>
+                            <label for={format!("first_{}", first)}>{label}</label>
+                        </>
+                    })}
+                </div>
                 <button type="submit">{"Submit Board Size"}</button>
             </form>
             <div class="">
                 <div class="post">
-                    <div>{"Game"}</div>
-                    <dive>{"Disc Colors: Red (You) vs Yellow (Computer)"}</dive>
+                    <div>{
+                        if board.variant() == Variant::Standard {
+                            format!("Game: Connect {}", board.win_length())
+                        } else {
+                            format!("Game: {}", board.variant().name())
+                        }
+                    }</div>
+                    <div class="flex items-center gap-2">
+                        {"Disc Colors:"}
+                        { for (1..=board.players()).map(|player| html! {
+                            <span class="flex items-center gap-1">
+                                <span class={format!("inline-block w-4 h-4 rounded-full {}", CHIP_COLORS[player - 1])} />
+                                {player_name(player, &seats, &names)}
+                            </span>
+                        })}
+                    </div>
+                    { if let Some((code, player)) = &*online_seat {
+                        html! {
+                            <div>{format!("Room {}: share the code for others to join. You are player {}.", code, player)}</div>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    { if let Some(status) = &*online_status {
+                        html! {<div>{status}</div>}
+                    } else {
+                        html! {}
+                    }}
+                    { if winner.is_none() {
+                        html! {
+                            <div class="flex items-center gap-2">
+                                {"Turn:"}
+                                <span class={format!("inline-block w-4 h-4 rounded-full {}", CHIP_COLORS[board.current_player() - 1])} />
+                                {player_name(board.current_player(), &seats, &names)}
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }}
                 </div>
                 <div>
                     <input type="radio" name="difficulty_easy" value="Easy"
@@ -172,32 +764,106 @@ pub fn Connect4Board() -> Html {
 
                     <input type="radio" name="difficulty_hard" value="Hard"
                         checked={*difficulty == "Hard"}
-                        onchange={handle_difficulty_change}/>
+                        onchange={handle_difficulty_change.clone()}/>
                     <label for="difficulty_hard">{"Hard mode (Play against minimax AI)"}</label>
+
+                    <input type="radio" name="difficulty_perfect" value="Perfect"
+                        checked={*difficulty == "Perfect"}
+                        disabled={!is_standard_board(&board)}
+                        onchange={handle_difficulty_change}/>
+                    <label for="difficulty_perfect">{"Perfect mode (standard 7x6 Connect 4 only)"}</label>
                 </div>
+                { if board.variant() == Variant::PopTen {
+                    html! {<div>{describe_pop_ten(&board, &seats, &names)}</div>}
+                } else {
+                    html! {}
+                }}
+                { if let Some(analysis) = &*analysis {
+                    html! {<div>{analysis}</div>}
+                } else {
+                    html! {}
+                }}
                 <div id="gameboard" class="w-[500px] border border-black bg-boardPrimaryBg px-6">
-                    { for (0..*rows).map(|y| html! {
+                    { for (0..board.rows()).map(|y| html! {
                         <div class="flex justify-center items-center">
-                            { for (0..*columns).map(|x| html! {
-                                <div class="relative flex w-full py-2  items-center justify-center" onclick={handle_user_move.reform(move |_| x)}>
+                            { for display_columns(&board).into_iter().map(|(x, is_wrapped)| html! {
+                                <div class="relative flex w-full py-2  items-center justify-center" onclick={handle_user_move.reform(move |_| Move::Drop(x))}>
                                     <div class="absolute inset-0 z-[-1]" />
                                     <div class={
-                                        let base_class = "w-12 h-12 aspect rounded-full flex";
-                                        let is_last_move = *last_move == Some((x, y));
+                                        // Faded copies of the opposite edge show lines that wrap around
+                                        let base_class = if is_wrapped {
+                                            "w-12 h-12 aspect rounded-full flex opacity-40"
+                                        } else {
+                                            "w-12 h-12 aspect rounded-full flex"
+                                        };
+                                        let is_last_move = board.last_move() == Some((x, y));
                                         let animation_class = if is_last_move { "animate-drop" } else { "" };
-                                        match board[y][x] {
-                                            1 => format!("{} {} {}", base_class, animation_class, "bg-chipPrimaryBg"),
-                                            2 => format!("{} {} {}", base_class, animation_class, "bg-chipSecondaryBg"),
-                                            _ => format!("{} {}", base_class, "bg-white"),
+                                        match board.cell(y, x) {
+                                            EMPTY => format!("{} {}", base_class, "bg-white"),
+                                            player => format!("{} {} {}", base_class, animation_class, CHIP_COLORS[player - 1]),
                                         }
                                     }></div>
                                 </div>
                             })}
                         </div>
                     })}
+                    { if matches!(board.variant(), Variant::PopOut | Variant::PopTen) {
+                        html! {
+                            <div class="flex justify-center items-center">
+                                { for display_columns(&board).into_iter().map(|(x, is_wrapped)| html! {
+                                    <div class="flex w-full py-2 items-center justify-center">
+                                        <button class="bg-violet-500 rounded-md px-2 text-white disabled:opacity-40"
+                                            disabled={is_wrapped || !*is_user_turn || !is_local_turn(&board, &seats, &online, &online_seat) || !board.can_pop(x)}
+                                            onclick={handle_user_move.reform(move |_| Move::Pop(x))}>
+                                            {"Pop"}
+                                        </button>
+                                    </div>
+                                })}
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                </div>
+                <div>
+                    <button class="bg-violet-500 rounded-md p-2 text-white disabled:opacity-40"
+                        disabled={!*is_user_turn || online.is_some() || take_back(&board, &seats).is_none()}
+                        onclick={on_undo}>
+                        {"Undo"}
+                    </button>
+                    <button class="bg-violet-500 rounded-md p-2 text-white disabled:opacity-40"
+                        disabled={!*is_user_turn || online.is_some() || redo.is_empty()}
+                        onclick={on_redo}>
+                        {"Redo"}
+                    </button>
+                </div>
+                <div>
+                    <button class="bg-violet-500 rounded-md p-2 text-white" onclick={on_export}>
+                        {"Show game record"}
+                    </button>
+                    <a class="bg-violet-500 rounded-md p-2 text-white" download="connect4.txt"
+                        href={format!("data:text/plain;charset=utf-8,{}", encode_uri_component(&game_record(&board, &seats, &names).to_string()))}>
+                        {"Download game record"}
+                    </a>
+                    <button class="bg-violet-500 rounded-md p-2 text-white disabled:opacity-40"
+                        disabled={online.is_some()}
+                        onclick={on_import}>
+                        {"Load game record"}
+                    </button>
+                    <div>
+                        <textarea class="w-[500px] h-40 border border-black font-mono"
+                            placeholder="Paste a game record here to load it"
+                            value={(*record_text).clone()} oninput={on_record_change} />
+                    </div>
+                    { if let Some(error) = &*record_error {
+                        html! {<div class="text-red-600">{error}</div>}
+                    } else {
+                        html! {}
+                    }}
                 </div>
                 { if let Some(winner) = *winner {
-                    html! {<WinnerModal winner={winner} />}
+                    html! {<WinnerModal winner={winner} winner_name={(winner != EMPTY).then(|| AttrValue::from(player_name(winner, &seats, &names)))}
+                        on_play_again={on_play_again} />}
                 } else {
                     html! {}
                 }}
@@ -206,169 +872,219 @@ pub fn Connect4Board() -> Html {
     }
 }
 
-fn check_draw(board: &Vec<Vec<usize>>) -> bool {
-    board[0].iter().all(|&cell| cell != EMPTY)
+/// Returns the solver's verdict on the position when playing perfectly
+fn make_computer_move(board: &mut Connect4Position, difficulty: &str) -> Option<Solution> {
+    let mut solution = None;
+    let mv = if difficulty == "Easy" {
+        *board.legal_moves().choose(&mut rand::thread_rng())?
+    } else if difficulty == "Perfect" {
+        solution = solve(board, PERFECT_WEB_TIME_BUDGET_MS);
+        // Too early in the game to solve in time, so fall back to Hard
+        solution.map_or_else(|| search(board, HARD_TIME_BUDGET_MS).0, |s| Move::Drop(s.column))
+    } else {
+        search(board, HARD_TIME_BUDGET_MS).0
+    };
+    log!(format!("Player {} played:", board.current_player()), format!("{:?}", mv));
+    board.play(mv);
+    solution
 }
 
-pub fn check_winner(board: &Vec<Vec<usize>>) -> Option<usize> {
-    let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
-    let rows = board.len(); // This gives you the number of rows
-    let columns = board.get(0).map_or(0, |row| row.len()); // This gives you the number of columns in the first row
-    for y in 0..rows {
-        for x in 0..columns {
-            if board[y][x] != 0 {
-                let current = board[y][x];
-                for (dy, dx) in directions.iter() {
-                    let mut count = 1;
-                    let mut nx = x as isize + dx;
-                    let mut ny = y as isize + dy;
-                    while nx >= 0
-                        && nx < columns as isize
-                        && ny >= 0
-                        && ny < rows as isize
-                        && board[ny as usize][nx as usize] == current
-                    {
-                        count += 1;
-                        if count == 4 {
-                            return Some(current);
-                        }
-                        nx += dx;
-                        ny += dy;
-                    }
-                }
+/// Moves for the computer seats after a short pause, until a human is to
+/// move or the game is over. Returns the timeout the computers' moves wait
+/// on, if they have any to make; dropping it calls them off.
+fn play_computer_turns(
+    mut new_board: Connect4Position,
+    seats: Vec<Seat>,
+    difficulty: String,
+    board: UseStateHandle<Connect4Position>,
+    winner: UseStateHandle<Option<usize>>,
+    is_user_turn: UseStateHandle<bool>,
+    analysis: UseStateHandle<Option<String>>,
+) -> Option<Timeout> {
+    if let Some(winner_player) = new_board.winner() {
+        winner.set(Some(winner_player));
+        return None;
+    } else if new_board.is_draw() {
+        winner.set(Some(EMPTY));
+        return None;
+    }
+    if seats[new_board.current_player() - 1] == Seat::Human {
+        is_user_turn.set(true);
+        return None;
+    }
+    is_user_turn.set(false);
+    Some(Timeout::new(500, move || {
+        // Computers may have several moves in a row, either from Pop 10 or
+        // from sitting next to each other
+        while seats[new_board.current_player() - 1] == Seat::Computer {
+            let solution = make_computer_move(&mut new_board, &difficulty);
+            analysis.set(if difficulty == "Perfect" {
+                Some(describe_solution(solution, &seats))
+            } else {
+                None
+            });
+            if let Some(winner_player) = new_board.winner() {
+                winner.set(Some(winner_player));
+                break;
+            } else if new_board.is_draw() {
+                winner.set(Some(EMPTY));
+                break;
             }
         }
-    }
-    None
+        board.set(new_board);
+        is_user_turn.set(true);
+    }))
 }
 
-fn get_next_open_row(board: &Vec<Vec<usize>>, col: usize) -> Option<usize> {
-    let rows = board.len(); // This gives you the number of rows
-    (0..rows)
-        .rev()
-        .find(|&row| board[row][col] == EMPTY)
+/// How a player is shown, by the name a human gave, or marking the computers
+/// and a lone human.
+fn player_name(player: usize, seats: &[Seat], names: &[String]) -> String {
+    let humans = seats.iter().filter(|&&seat| seat == Seat::Human).count();
+    let name = names[player - 1].trim();
+    match seats[player - 1] {
+        Seat::Human if !name.is_empty() => name.to_string(),
+        Seat::Human if humans == 1 => format!("Player {} (You)", player),
+        Seat::Human => format!("Player {}", player),
+        Seat::Computer => format!("Player {} (Computer)", player),
+    }
 }
 
-fn score_position(board: &Vec<Vec<usize>>, piece: usize) -> isize {
-    let rows = board.len(); // This gives you the number of rows
-    let columns = board.get(0).map_or(0, |row| row.len()); // This gives you the number of columns in the first row
-
-    let mut score = 0;
-    let center_col = columns / 2;
-
-    let center_count = board
-        .iter()
-        .map(|row| (row[center_col] == piece) as isize)
-        .sum::<isize>();
-    score += center_count * 10;
-
-    // Horizontal windows
-    for row in board {
-        for col in 0..=columns - 4 {
-            let window = &row[col..col + 4];
-            score += evaluate_window(window, piece);
-        }
+/// Whether the player to move is at this device: a human seat, or the seat
+/// this board holds in an online room.
+fn is_local_turn(
+    board: &Connect4Position,
+    seats: &[Seat],
+    online: &Option<Connection>,
+    online_seat: &Option<(String, usize)>,
+) -> bool {
+    match online {
+        Some(_) => online_seat
+            .as_ref()
+            .is_some_and(|(_, player)| *player == board.current_player()),
+        None => seats[board.current_player() - 1] == Seat::Human,
     }
+}
 
-    // Vertical windows
-    for col in 0..columns {
-        for row in 0..=rows - 4 {
-            let window = (0..4).map(|i| board[row + i][col]).collect::<Vec<_>>();
-            score += evaluate_window(&window, piece);
-        }
+/// Who sits in the first seat under the "First move" setting, given who sat
+/// there last game, if there was one.
+fn first_seat(choice: &str, last_first: Option<Seat>) -> Seat {
+    match choice {
+        "Computer" => Seat::Computer,
+        "Random" if rand::random() => Seat::Computer,
+        "Alternate" if last_first == Some(Seat::Human) => Seat::Computer,
+        _ => Seat::Human,
     }
+}
 
-    // Positive Diagonal windows
-    for row in 0..=rows - 4 {
-        for col in 0..=columns - 4 {
-            let window = (0..4).map(|i| board[row + i][col + i]).collect::<Vec<_>>();
-            score += evaluate_window(&window, piece);
+/// Moves everyone round the table with their names, keeping the order of
+/// play, to follow the "First move" setting. When people play computers it
+/// picks which of them opens, going by who sat first last game. At a table
+/// of only people, or only computers, "Alternate" hands the first move on to
+/// the next player and "Random" to anyone.
+fn seat_first(seats: &mut [Seat], names: &mut [String], choice: &str, last_first: Option<Seat>) {
+    let turns = if seats.iter().all(|&seat| seat == seats[0]) {
+        match choice {
+            "Random" => rand::thread_rng().gen_range(0..seats.len()),
+            "Alternate" => 1,
+            _ => 0,
         }
-    }
+    } else {
+        let first = first_seat(choice, last_first);
+        seats.iter().position(|&seat| seat == first).unwrap_or(0)
+    };
+    seats.rotate_left(turns);
+    names.rotate_left(turns);
+}
 
-    // Negative Diagonal windows
-    for row in 3..rows {
-        for col in 0..=columns - 4 {
-            let window = (0..4).map(|i| board[row - i][col + i]).collect::<Vec<_>>();
-            score += evaluate_window(&window, piece);
+/// The "First move" options and their labels. Without both people and
+/// computers playing there is no side to pick, only the order to keep.
+fn first_choices(seats: &[Seat]) -> Vec<(&'static str, &'static str)> {
+    let mut choices = if seats.contains(&Seat::Human) && seats.contains(&Seat::Computer) {
+        vec![("User", "You"), ("Computer", "Computer")]
+    } else {
+        vec![("User", "Player 1")]
+    };
+    choices.extend([("Random", "Random"), ("Alternate", "Take turns")]);
+    choices
+}
+
+/// The board from before the last move made by a human, and the moves taken
+/// back to get there, latest first. `None` if no human has moved yet.
+fn take_back(board: &Connect4Position, seats: &[Seat]) -> Option<(Connect4Position, Vec<Move>)> {
+    let mut board = board.clone();
+    let mut undone = Vec::new();
+    while let Some(mv) = board.undo() {
+        undone.push(mv);
+        if seats[board.current_player() - 1] == Seat::Human {
+            return Some((board, undone));
         }
     }
+    None
+}
 
-    score
+/// The game so far, with the players named as they are shown on the page.
+fn game_record(board: &Connect4Position, seats: &[Seat], names: &[String]) -> Connect4Record {
+    let names = (1..=board.players())
+        .map(|player| player_name(player, seats, names))
+        .collect();
+    Connect4Record::new(board, names, today())
 }
 
-pub fn minimax(
-    board: &Vec<Vec<usize>>,
-    depth: usize,
-    mut alpha: isize,
-    mut beta: isize,
-    is_maximizing: bool,
-) -> (usize, isize) {
-    if depth == 0 || check_winner(board).is_some() {
-        return (0, score_position(board, COMPUTER));
+fn is_standard_board(board: &Connect4Position) -> bool {
+    board.rules() == Rules::default()
+}
+
+/// The columns to draw from left to right, and whether each is a copy of the
+/// opposite edge. A cylinder gets one extra column on either side.
+fn display_columns(board: &Connect4Position) -> Vec<(usize, bool)> {
+    let columns = board.columns();
+    let mut display: Vec<(usize, bool)> = (0..columns).map(|x| (x, false)).collect();
+    if board.topology() == Topology::Cylinder {
+        display.insert(0, (columns - 1, true));
+        display.push((0, true));
     }
+    display
+}
 
-    if is_maximizing {
-        let mut value = isize::MIN;
-        let mut column = usize::MAX;
-
-        let columns = board.get(0).map_or(0, |row| row.len()); // This gives you the number of columns in the first row
-        for col in 0..columns {
-            if let Some(row) = get_next_open_row(board, col) {
-                let mut temp_board = board.clone();
-                temp_board[row][col] = COMPUTER;
-                let new_score = minimax(&temp_board, depth - 1, alpha, beta, false).1;
-                if new_score > value {
-                    value = new_score;
-                    column = col;
-                }
-                alpha = max(alpha, value);
-                if alpha >= beta {
-                    break;
-                }
-            }
-        }
-        (column, value)
+/// What the player to move has to do next in Pop 10, and the discs
+/// collected so far.
+fn describe_pop_ten(board: &Connect4Position, seats: &[Seat], names: &[String]) -> String {
+    let step = if board.is_setup() {
+        "Fill the board one row at a time"
+    } else if board.must_drop() {
+        "Drop the disc you popped back into any column"
     } else {
-        let mut value = isize::MAX;
-        let mut column = usize::MAX;
-
-        let columns = board.get(0).map_or(0, |row| row.len()); // This gives you the number of columns in the first row
-        for col in 0..columns {
-            if let Some(row) = get_next_open_row(board, col) {
-                let mut temp_board = board.clone();
-                temp_board[row][col] = USER;
-                let new_score = minimax(&temp_board, depth - 1, alpha, beta, true).1;
-                if new_score < value {
-                    value = new_score;
-                    column = col;
-                }
-                beta = min(beta, value);
-                if alpha >= beta {
-                    break;
-                }
-            }
-        }
-        (column, value)
-    }
+        "Pop one of your discs from the bottom row"
+    };
+    let collected: Vec<String> = (1..=board.players())
+        .map(|player| format!("{} for {}", board.collected(player), player_name(player, seats, names)))
+        .collect();
+    format!(
+        "{}: {}. Collected: {}, first to {}",
+        player_name(board.current_player(), seats, names),
+        step,
+        collected.join(" vs "),
+        POP_TEN_TARGET
+    )
 }
 
-fn evaluate_window(window: &[usize], piece: usize) -> isize {
-    let mut score = 0;
-    let opp_piece = if piece == USER { COMPUTER } else { USER };
-    let count_piece = window.iter().filter(|&&p| p == piece).count();
-    let count_empty = window.iter().filter(|&&p| p == EMPTY).count();
-    let count_opp_piece = window.iter().filter(|&&p| p == opp_piece).count();
-
-    match (count_piece, count_empty, count_opp_piece) {
-        (4, 0, 0) => score += 10000,
-        (0, 0, 4) => score -= 10000,
-        (3, 1, 0) => score += 500,
-        (0, 1, 3) => score -= 500,
-        (2, 2, 0) => score += 50,
-        (0, 2, 2) => score -= 50,
-        _ => (),
+/// Describes the solver's verdict from just after the computer's reply,
+/// counting moves for the winning side only. The solver only runs for two
+/// players.
+fn describe_solution(solution: Option<Solution>, seats: &[Seat]) -> String {
+    let Some(solution) = solution else {
+        return "Perfect play: too early to solve, playing the best move found".to_string();
+    };
+    // The computer's reply was the first of these discs, and the other
+    // player moves next
+    let remaining = solution.moves_to_end - 1;
+    match solution.winner {
+        Some(player) if seats[player - 1] == Seat::Computer => match remaining / 2 {
+            // The reply itself won
+            0 => "Perfect play: the computer has won".to_string(),
+            moves => format!("Perfect play: forced win for the computer in {}", moves),
+        },
+        Some(_) => format!("Perfect play: forced win for you in {}", remaining.div_ceil(2)),
+        None => "Perfect play: the game is a draw".to_string(),
     }
-
-    score
 }
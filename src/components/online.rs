@@ -0,0 +1,204 @@
+//! The web boards' connection to the game server started by `serve`. A
+//! dropped socket is opened again by itself, and takes back the same seat,
+//! until the server has stayed away for a few tries.
+
+use crate::engine::online::{ClientMessage, ServerMessage};
+use gloo_console::log;
+use gloo_timers::callback::Timeout;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{CloseEvent, MessageEvent, WebSocket};
+use yew::Callback;
+
+/// How long to wait before trying the server again the first time. Each
+/// try after that waits twice as long as the last.
+const RECONNECT_DELAY_MS: u32 = 1000;
+
+/// How many times to try the server again before giving up on the room.
+const RECONNECT_TRIES: u32 = 5;
+
+/// What a board hears from its connection.
+pub enum OnlineEvent {
+    Message(ServerMessage),
+    /// The socket dropped, and is being opened again.
+    Reconnecting,
+    /// The server couldn't be reached again, and the board has left the
+    /// room.
+    Disconnected,
+}
+
+/// A board's connection to one room.
+pub struct Connection(Rc<RefCell<Inner>>);
+
+struct Inner {
+    url: String,
+    socket: Option<WebSocket>,
+    /// Creates or joins the room when the first socket opens.
+    opening: ClientMessage,
+    /// The room code and seat token to rejoin with, once the server has
+    /// given them.
+    seat: Option<(String, String)>,
+    on_event: Callback<OnlineEvent>,
+    /// Set once the board is done with the room, so it stays closed.
+    is_closed: bool,
+    /// The tries made since the socket was last open.
+    reconnects: u32,
+    // The socket's handlers, kept alive as long as it is
+    on_open: Option<Closure<dyn FnMut()>>,
+    on_message: Option<Closure<dyn FnMut(MessageEvent)>>,
+    on_close: Option<Closure<dyn FnMut(CloseEvent)>>,
+}
+
+impl Inner {
+    fn close(&mut self) {
+        self.is_closed = true;
+        if let Some(socket) = self.socket.take() {
+            // The handlers go with the connection, before the socket is done
+            socket.set_onopen(None);
+            socket.set_onmessage(None);
+            socket.set_onclose(None);
+            let _ = socket.close();
+        }
+    }
+}
+
+/// A board that goes away leaves the room too.
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl Connection {
+    /// Connects to the server at `url` and sends `opening`, which should
+    /// create or join a room.
+    pub fn open(url: &str, opening: ClientMessage, on_event: Callback<OnlineEvent>) -> Self {
+        let connection = Connection(Rc::new(RefCell::new(Inner {
+            url: url.to_string(),
+            socket: None,
+            opening,
+            seat: None,
+            on_event,
+            is_closed: false,
+            reconnects: 0,
+            on_open: None,
+            on_message: None,
+            on_close: None,
+        })));
+        connection.connect();
+        connection
+    }
+
+    /// Sends a request to the room. One sent while the socket is down is
+    /// lost, and the board still shows the move wasn't made.
+    pub fn send(&self, message: &ClientMessage) {
+        if let Some(socket) = &self.0.borrow().socket {
+            if socket.send_with_str(&message.to_string()).is_err() {
+                log!("Can't send to the game server");
+            }
+        }
+    }
+
+    /// Leaves the room for good.
+    pub fn close(&self) {
+        self.0.borrow_mut().close();
+    }
+
+    fn connect(&self) {
+        let url = self.0.borrow().url.clone();
+        let socket = match WebSocket::new(&url) {
+            Ok(socket) => socket,
+            Err(_) => {
+                let on_event = {
+                    let mut inner = self.0.borrow_mut();
+                    inner.is_closed = true;
+                    inner.on_event.clone()
+                };
+                on_event.emit(OnlineEvent::Message(ServerMessage::Error(format!(
+                    "{} is not a WebSocket address",
+                    url
+                ))));
+                return;
+            }
+        };
+
+        let weak = Rc::downgrade(&self.0);
+        let on_open = Closure::<dyn FnMut()>::new(move || {
+            let Some(inner) = weak.upgrade() else { return };
+            let mut inner = inner.borrow_mut();
+            inner.reconnects = 0;
+            // Coming back takes the same seat rather than a new one
+            let message = match &inner.seat {
+                Some((code, token)) => ClientMessage::Rejoin {
+                    code: code.clone(),
+                    token: token.clone(),
+                },
+                None => inner.opening.clone(),
+            };
+            if let Some(socket) = &inner.socket {
+                let _ = socket.send_with_str(&message.to_string());
+            }
+        });
+
+        let weak = Rc::downgrade(&self.0);
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let Some(inner) = weak.upgrade() else { return };
+            let Some(text) = event.data().as_string() else {
+                return;
+            };
+            let message = match text.parse::<ServerMessage>() {
+                Ok(message) => message,
+                Err(error) => {
+                    log!(format!("Can't read the game server's message: {}", error));
+                    return;
+                }
+            };
+            if let ServerMessage::Joined { code, token, .. } = &message {
+                inner.borrow_mut().seat = Some((code.clone(), token.clone()));
+            }
+            // The board may send a reply straight away
+            let on_event = inner.borrow().on_event.clone();
+            on_event.emit(OnlineEvent::Message(message));
+        });
+
+        let weak = Rc::downgrade(&self.0);
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_: CloseEvent| {
+            let Some(inner) = weak.upgrade() else { return };
+            if inner.borrow().is_closed {
+                return;
+            }
+            let (on_event, reconnects) = {
+                let mut inner = inner.borrow_mut();
+                inner.reconnects += 1;
+                (inner.on_event.clone(), inner.reconnects)
+            };
+            if reconnects > RECONNECT_TRIES {
+                inner.borrow_mut().close();
+                on_event.emit(OnlineEvent::Disconnected);
+                return;
+            }
+            on_event.emit(OnlineEvent::Reconnecting);
+            let weak = Rc::downgrade(&inner);
+            Timeout::new(RECONNECT_DELAY_MS << (reconnects - 1), move || {
+                if let Some(inner) = weak.upgrade() {
+                    if !inner.borrow().is_closed {
+                        Connection(inner).connect();
+                    }
+                }
+            })
+            .forget();
+        });
+
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        // The old socket has closed, so its handlers can go
+        let mut inner = self.0.borrow_mut();
+        inner.socket = Some(socket);
+        inner.on_open = Some(on_open);
+        inner.on_message = Some(on_message);
+        inner.on_close = Some(on_close);
+    }
+}
@@ -1,119 +1,118 @@
-use crate::constant::{DEFAULT_OT_COLS, DEFAULT_OT_ROWS, HEADER, RED_BAR};
+use crate::components::online::{Connection, OnlineEvent};
+use crate::constant::{COMPUTER, DEFAULT_SERVER_PORT, HEADER, RED_BAR, USER};
+use crate::engine::clock::today;
+use crate::engine::online::{ClientMessage, OnlineGame, ServerMessage};
+use crate::engine::record::TootOttoRecord;
+use crate::engine::tootandotto::{
+    best_move, random_move, TootOttoPosition, TootOttoRules, MAX_SIZE,
+};
 use gloo_timers::callback::Timeout;
-use rand::prelude::*;
-use web_sys::HtmlInputElement;
+use rand::Rng;
+use web_sys::js_sys::encode_uri_component;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
 use yew::prelude::*;
 use yew::{function_component, html};
 
 #[function_component]
 pub fn TootAndOttoBoard() -> Html {
-    let board = use_state(|| vec![vec![(' ', 0); DEFAULT_OT_COLS]; DEFAULT_OT_ROWS]);
-    let player_turn = use_state(|| 1);
+    let input_rows = use_state(|| TootOttoRules::default().rows);
+    let input_columns = use_state(|| TootOttoRules::default().columns);
+    // "TOOT", "OTTO" or "Random", the word the user wants to spell
+    let input_side = use_state(|| "TOOT".to_string());
+    // "User", "Computer", "Random" or "Alternate", who moves first each game
+    let input_first = use_state(|| "User".to_string());
+    // Whether a game has been started here yet, as "Alternate" lets the user
+    // open the first one
+    let started = use_state(|| false);
+    // "Computer", "Human" for two people taking turns on this device, or
+    // "Online" for someone else on the game server
+    let input_opponent = use_state(|| "Computer".to_string());
+    let input_names = use_state(|| vec![String::new(); 2]);
+    // The game server, and the room to join there, blank to open a new one
+    let input_server = use_state(|| format!("ws://localhost:{}", DEFAULT_SERVER_PORT));
+    let input_room = use_state(String::new);
+
+    let board = use_state(|| TootOttoPosition::new(TootOttoRules::default()));
     let player_choice = use_state(|| 'T');
     let winner = use_state(|| None::<usize>);
     let difficulty = use_state(|| "Easy".to_string());
-    let last_move = use_state(|| None::<(usize, usize)>);
     let is_user_turn = use_state(|| true);
-    let player_t_pieces = use_state(|| vec![6, 6]); // First idx is user, second is computer
-    let player_o_pieces = use_state(|| vec![6, 6]);
+    // The names of players 1 and 2 when people play each other, here or
+    // online, `None` against the computer
+    let names = use_state(|| None::<Vec<String>>);
+    // Letters taken back with Undo, the next one to play again last
+    let redo = use_state(Vec::<(usize, char)>::new);
+    // The game record being exported or pasted in, and why it couldn't be
+    // loaded
+    let record_text = use_state(String::new);
+    let record_error = use_state(|| None::<String>);
+    // The room on the game server, this board's code and player number
+    // there, and how the connection is doing
+    let online = use_state(|| None::<Connection>);
+    let online_seat = use_state(|| None::<(String, usize)>);
+    let online_status = use_state(|| None::<String>);
+    // The computer's letter waiting to be placed. Dropping it cancels the
+    // move, which every new game does so it can't land on the fresh board.
+    let computer_turn = use_mut_ref(|| None::<Timeout>);
 
     let handle_click = {
         let board = board.clone();
-        let player_turn = player_turn.clone();
         let player_choice = player_choice.clone();
         let winner = winner.clone();
         let difficulty = difficulty.clone();
-        let last_move = last_move.clone();
         let is_user_turn = is_user_turn.clone();
-        let player_t_pieces = player_t_pieces.clone();
-        let player_o_pieces = player_o_pieces.clone();
+        let names = names.clone();
+        let redo = redo.clone();
+        let online = online.clone();
+        let online_seat = online_seat.clone();
+        let computer_turn = computer_turn.clone();
 
         Callback::from(move |x: usize| {
             if !*is_user_turn {
                 return;
             }
+            // The server places the letter and sends everyone the new board
+            if let Some(connection) = &*online {
+                let is_my_turn = online_seat
+                    .as_ref()
+                    .is_some_and(|(_, player)| *player == board.current_player());
+                if is_my_turn && winner.is_none() {
+                    connection.send(&ClientMessage::TootOttoMove(x, *player_choice));
+                }
+                return;
+            }
             if winner.is_none() {
                 let mut new_board = (*board).clone();
-                let mut new_player_t_pieces = (*player_t_pieces).clone();
-                let mut new_player_o_pieces = (*player_o_pieces).clone();
-
-                // Check if the player has enough tokens
-                let choice = *player_choice;
-                let (player_tokens, opponent_tokens) = if choice == 'T' {
-                    (&mut new_player_t_pieces, &mut new_player_o_pieces)
-                } else {
-                    (&mut new_player_o_pieces, &mut new_player_t_pieces)
-                };
-
-                if player_tokens[0] == 0 {
-                    // If the player has run out of tokens, return without processing the click event
-                    return;
-                }
-
-                if let Some(y) = (0..DEFAULT_OT_ROWS)
-                    .rev()
-                    .find(|&y| new_board[y][x].0 == ' ')
-                {
-                    new_board[y][x] = (choice, *player_turn);
-                    last_move.set(Some((x, y)));
-                    is_user_turn.set(false);
-
-                    // Decrement the player's token count
-                    player_tokens[0] -= 1;
+                let mover = new_board.current_player();
 
+                // Returns without doing anything if the column is full or the
+                // player has run out of the chosen letter
+                if new_board.play(x, *player_choice).is_some() {
+                    // A new move makes the ones taken back meaningless
+                    redo.set(Vec::new());
                     board.set(new_board.clone());
 
-                    if let Some(win_player) = check_winner(&new_board) {
+                    if let Some(win_player) = new_board.winner() {
                         winner.set(Some(win_player));
-                    } else if is_full_board(&new_board) {
+                    } else if new_board.is_draw() {
                         winner.set(Some(3));
                     } else {
-                        let new_board = new_board.clone();
-                        let last_move = last_move.clone();
-                        let difficulty = difficulty.clone();
-                        let winner = winner.clone();
-                        let player_turn = player_turn.clone();
-                        let is_user_turn = is_user_turn.clone();
-                        let board = board.clone();
-                        let new_player_t_pieces = new_player_t_pieces.clone();
-                        let new_player_o_pieces = new_player_o_pieces.clone();
-                        let player_t_pieces = player_t_pieces.clone();
-                        let player_o_pieces = player_o_pieces.clone();
-                        player_turn.set(2);
-
-                        let timeout = Timeout::new(500, move || {
-                            let difficulty = difficulty.clone();
-                            let mut new_board = new_board;
-                            let mut new_player_t_pieces = new_player_t_pieces;
-                            let mut new_player_o_pieces = new_player_o_pieces;
-                            if *difficulty == "Hard" {
-                                make_computer_move(
-                                    &mut new_board,
-                                    2,
-                                    &mut new_player_t_pieces,
-                                    &mut new_player_o_pieces,
-                                );
-                            } else {
-                                make_random_computer_move(
-                                    &mut new_board,
-                                    &mut new_player_t_pieces,
-                                    &mut new_player_o_pieces,
-                                );
-                            }
-                            last_move.set(Some((x, y)));
-                            if let Some(win_player) = check_winner(&new_board) {
-                                winner.set(Some(win_player));
-                            } else if is_full_board(&new_board) {
-                                winner.set(Some(3));
-                            } else {
-                                player_turn.set(1);
-                            }
-                            board.set(new_board);
-                            player_t_pieces.set(new_player_t_pieces);
-                            player_o_pieces.set(new_player_o_pieces);
-                            is_user_turn.set(true);
-                        });
-                        timeout.forget();
+                        // Whoever picks a letter next may be out of the one
+                        // just used: the other player in a hot-seat game, or
+                        // this one again against the computer
+                        let player = if names.is_some() { new_board.current_player() } else { mover };
+                        if new_board.letters_left(player, *player_choice) == 0 {
+                            player_choice.set(if *player_choice == 'T' { 'O' } else { 'T' });
+                        }
+                        if names.is_none() {
+                            *computer_turn.borrow_mut() = Some(play_computer_turn(
+                                new_board,
+                                (*difficulty).clone(),
+                                board.clone(),
+                                winner.clone(),
+                                is_user_turn.clone(),
+                            ));
+                        }
                     }
                 }
             }
@@ -121,18 +120,18 @@ pub fn TootAndOttoBoard() -> Html {
     };
 
     let handle_option_change = {
+        let board = board.clone();
         let player_choice = player_choice.clone();
-        let player_t_pieces = player_t_pieces.clone();
-        let player_o_pieces = player_o_pieces.clone();
 
         Callback::from(move |e: Event| {
             let input: HtmlInputElement = e.target_unchecked_into();
             let choice = input.value().chars().next().unwrap_or('T');
+            let player = board.current_player();
 
-            if choice == 'T' && player_t_pieces[0] == 0 {
+            if choice == 'T' && board.letters_left(player, 'T') == 0 {
                 // Change player's choice to 'O' if they run out of 'T' tokens
                 player_choice.set('O');
-            } else if choice == 'O' && player_o_pieces[0] == 0 {
+            } else if choice == 'O' && board.letters_left(player, 'O') == 0 {
                 // Change player's choice to 'T' if they run out of 'O' tokens
                 player_choice.set('T');
             } else {
@@ -149,8 +148,450 @@ pub fn TootAndOttoBoard() -> Html {
         })
     };
 
+    // Keeps the board in step with the room on the game server
+    let on_online_event = {
+        let board = board.clone();
+        let player_choice = player_choice.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let names = names.clone();
+        let online_seat = online_seat.clone();
+        let online_status = online_status.clone();
+        Callback::from(move |event: OnlineEvent| match event {
+            OnlineEvent::Message(ServerMessage::Joined { code, player, .. }) => {
+                online_seat.set(Some((code, player)));
+            }
+            OnlineEvent::Message(ServerMessage::State {
+                game: OnlineGame::TootOtto(record),
+                open_seats,
+            }) => {
+                let Ok(new_board) = record.position() else {
+                    return;
+                };
+                winner.set(if let Some(win_player) = new_board.winner() {
+                    Some(win_player)
+                } else if new_board.is_draw() {
+                    Some(3)
+                } else {
+                    None
+                });
+                // The player to move may be out of one of the letters
+                let player = new_board.current_player();
+                if new_board.letters_left(player, 'T') == 0 {
+                    player_choice.set('O');
+                } else if new_board.letters_left(player, 'O') == 0 {
+                    player_choice.set('T');
+                }
+                board.set(new_board);
+                names.set(Some(record.names));
+                is_user_turn.set(true);
+                online_status.set((open_seats > 0).then(|| "Waiting for the other player to join".to_string()));
+            }
+            OnlineEvent::Message(ServerMessage::State { .. }) => {
+                online_status.set(Some("That room is playing Connect 4".to_string()));
+            }
+            OnlineEvent::Message(ServerMessage::Error(error)) => {
+                online_status.set(Some(format!("Can't do that: {}", error)));
+            }
+            OnlineEvent::Reconnecting => {
+                online_status.set(Some("Lost the connection, reconnecting...".to_string()));
+            }
+            OnlineEvent::Disconnected => {
+                online_status.set(Some("Can't reach the game server, start a new game to try again".to_string()));
+            }
+        })
+    };
+
+    // Updates the state when the form is submitted, not when the inputs change
+    let on_submit = {
+        let board = board.clone();
+        let input_rows = input_rows.clone();
+        let input_columns = input_columns.clone();
+        let input_side = input_side.clone();
+        let input_first = input_first.clone();
+        let started = started.clone();
+        let input_opponent = input_opponent.clone();
+        let input_names = input_names.clone();
+        let input_server = input_server.clone();
+        let input_room = input_room.clone();
+        let player_choice = player_choice.clone();
+        let winner = winner.clone();
+        let difficulty = difficulty.clone();
+        let is_user_turn = is_user_turn.clone();
+        let names = names.clone();
+        let redo = redo.clone();
+        let online = online.clone();
+        let online_seat = online_seat.clone();
+        let online_status = online_status.clone();
+        let computer_turn = computer_turn.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let rows = (*input_rows).clamp(4, MAX_SIZE);
+            let columns = (*input_columns).clamp(4, MAX_SIZE);
+            let user_is_toot = match input_side.as_str() {
+                "OTTO" => false,
+                "Random" => rand::thread_rng().gen_bool(0.5),
+                _ => true,
+            };
+            let rules = TootOttoRules {
+                toot_player: if user_is_toot { USER } else { COMPUTER },
+                first_player: first_player(&input_first, started.then_some(board.rules().first_player)),
+                ..TootOttoRules::for_board(rows, columns)
+            };
+            let is_hot_seat = *input_opponent == "Human";
+            input_rows.set(rows);
+            input_columns.set(columns);
+            // A new game leaves the room this board was in
+            if let Some(connection) = &*online {
+                connection.close();
+            }
+            online_seat.set(None);
+            if *input_opponent == "Online" {
+                let new_board = TootOttoPosition::new(rules);
+                let opening = if input_room.trim().is_empty() {
+                    let record = TootOttoRecord::new(&new_board, vec![input_names[0].clone(), String::new()], today());
+                    ClientMessage::Create(OnlineGame::TootOtto(record))
+                } else {
+                    ClientMessage::Join {
+                        code: input_room.trim().to_string(),
+                        name: input_names[0].clone(),
+                    }
+                };
+                // The server sends the board and the players once it has
+                // found the room
+                // Set first, as a bad address is reported while opening
+                online_status.set(Some("Connecting to the game server...".to_string()));
+                online.set(Some(Connection::open(&input_server, opening, on_online_event.clone())));
+                board.set(new_board);
+                names.set(Some(vec![String::new(); 2]));
+                winner.set(None);
+                redo.set(Vec::new());
+                computer_turn.borrow_mut().take();
+                is_user_turn.set(true);
+                return;
+            }
+            online.set(None);
+            online_status.set(None);
+            names.set(is_hot_seat.then(|| (*input_names).clone()));
+            started.set(true);
+            *computer_turn.borrow_mut() = start_game(
+                TootOttoPosition::new(rules),
+                (!is_hot_seat).then(|| (*difficulty).clone()),
+                board.clone(),
+                player_choice.clone(),
+                winner.clone(),
+                is_user_turn.clone(),
+                redo.clone(),
+            );
+        })
+    };
+
+    // A new game on the same board, with the same words and difficulty
+    let on_play_again = {
+        let board = board.clone();
+        let input_first = input_first.clone();
+        let player_choice = player_choice.clone();
+        let winner = winner.clone();
+        let difficulty = difficulty.clone();
+        let is_user_turn = is_user_turn.clone();
+        let names = names.clone();
+        let redo = redo.clone();
+        let online = online.clone();
+        let computer_turn = computer_turn.clone();
+        Callback::from(move |swap_first: bool| {
+            // The room starts over for both players
+            if let Some(connection) = &*online {
+                connection.send(&ClientMessage::PlayAgain { swap_first });
+                return;
+            }
+            let mut rules = board.rules();
+            rules.first_player = if swap_first {
+                first_player("Alternate", Some(rules.first_player))
+            } else {
+                first_player(&input_first, Some(rules.first_player))
+            };
+            *computer_turn.borrow_mut() = start_game(
+                TootOttoPosition::new(rules),
+                names.is_none().then(|| (*difficulty).clone()),
+                board.clone(),
+                player_choice.clone(),
+                winner.clone(),
+                is_user_turn.clone(),
+                redo.clone(),
+            );
+        })
+    };
+
+    // Takes back the user's last letter along with the computer's reply, or
+    // just the last letter in a hot-seat game
+    let on_undo = {
+        let board = board.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let names = names.clone();
+        let redo = redo.clone();
+        Callback::from(move |_: MouseEvent| {
+            if is_computer_thinking(&is_user_turn, &winner) {
+                return;
+            }
+            if let Some((new_board, undone)) = take_back(&board, names.is_some()) {
+                let mut new_redo = (*redo).clone();
+                new_redo.extend(undone);
+                board.set(new_board);
+                redo.set(new_redo);
+                winner.set(None);
+                is_user_turn.set(true);
+            }
+        })
+    };
+
+    // Places the last letter taken back again, and the computer's reply
+    let on_redo = {
+        let board = board.clone();
+        let winner = winner.clone();
+        let is_user_turn = is_user_turn.clone();
+        let names = names.clone();
+        let redo = redo.clone();
+        Callback::from(move |_: MouseEvent| {
+            if is_computer_thinking(&is_user_turn, &winner) {
+                return;
+            }
+            let mut new_board = (*board).clone();
+            let mut new_redo = (*redo).clone();
+            while let Some((col, letter)) = new_redo.pop() {
+                new_board.play(col, letter);
+                if new_redo.is_empty() || names.is_some() || new_board.current_player() == USER {
+                    break;
+                }
+            }
+            if let Some(win_player) = new_board.winner() {
+                winner.set(Some(win_player));
+            } else if new_board.is_draw() {
+                winner.set(Some(3));
+            }
+            board.set(new_board);
+            redo.set(new_redo);
+        })
+    };
+
+    let on_export = {
+        let board = board.clone();
+        let names = names.clone();
+        let record_text = record_text.clone();
+        let record_error = record_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            record_text.set(game_record(&board, &names).to_string());
+            record_error.set(None);
+        })
+    };
+
+    let on_record_change = {
+        let record_text = record_text.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            record_text.set(input.value());
+        })
+    };
+
+    // Starts over from the pasted record, with the user as player 1 unless two
+    // people are playing
+    let on_import = {
+        let board = board.clone();
+        let player_choice = player_choice.clone();
+        let computer_turn = computer_turn.clone();
+        let input_rows = input_rows.clone();
+        let input_columns = input_columns.clone();
+        let input_side = input_side.clone();
+        let winner = winner.clone();
+        let difficulty = difficulty.clone();
+        let is_user_turn = is_user_turn.clone();
+        let names = names.clone();
+        let record_text = record_text.clone();
+        let record_error = record_error.clone();
+        let redo = redo.clone();
+        Callback::from(move |_: MouseEvent| {
+            let new_board = match record_text
+                .parse::<TootOttoRecord>()
+                .and_then(|record| record.position())
+            {
+                Ok(new_board) => new_board,
+                Err(error) => {
+                    record_error.set(Some(format!("Can't load the game: {}", error)));
+                    return;
+                }
+            };
+            board.set(new_board.clone());
+            // As for a new game, unless the player to move is out of T's
+            let player = new_board.current_player();
+            player_choice.set(if new_board.letters_left(player, 'T') == 0 { 'O' } else { 'T' });
+            input_rows.set(new_board.rows());
+            input_columns.set(new_board.columns());
+            input_side.set(new_board.word(USER).name().to_string());
+            record_error.set(None);
+            redo.set(Vec::new());
+            computer_turn.borrow_mut().take();
+            if let Some(win_player) = new_board.winner() {
+                winner.set(Some(win_player));
+            } else if new_board.is_draw() {
+                winner.set(Some(3));
+            } else if new_board.current_player() == COMPUTER && names.is_none() {
+                winner.set(None);
+                *computer_turn.borrow_mut() = Some(play_computer_turn(
+                    new_board,
+                    (*difficulty).clone(),
+                    board.clone(),
+                    winner.clone(),
+                    is_user_turn.clone(),
+                ));
+            } else {
+                winner.set(None);
+                is_user_turn.set(true);
+            }
+        })
+    };
+
+    // Handlers to update input fields values
+    let on_rows_change = {
+        let input_rows = input_rows.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+                input_rows.set(input.value_as_number() as usize);
+            }
+        })
+    };
+
+    let on_first_change = {
+        let input_first = input_first.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_first.set(input.value());
+        })
+    };
+
+    let on_opponent_change = {
+        let input_opponent = input_opponent.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_opponent.set(input.value());
+        })
+    };
+
+    let on_name_change = {
+        let input_names = input_names.clone();
+        Callback::from(move |(player, e): (usize, InputEvent)| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut new_names = (*input_names).clone();
+            new_names[player] = input.value();
+            input_names.set(new_names);
+        })
+    };
+
+    let on_server_change = {
+        let input_server = input_server.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_server.set(input.value());
+        })
+    };
+
+    let on_room_change = {
+        let input_room = input_room.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_room.set(input.value());
+        })
+    };
+
+    let on_side_change = {
+        let input_side = input_side.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            input_side.set(input.value());
+        })
+    };
+
+    let on_cols_change = {
+        let input_columns = input_columns.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+                input_columns.set(input.value_as_number() as usize);
+            }
+        })
+    };
+
     html! {
         <div>
+            <form onsubmit={on_submit}>
+                <div>
+                    <label for="ot_rows_input">{"Rows:"}</label>
+                    <input id="ot_rows_input" type="number" min="4" max="10" value={(*input_rows).to_string()} oninput={on_rows_change} />
+                </div>
+                <div>
+                    <label for="ot_cols_input">{"Columns:"}</label>
+                    <input id="ot_cols_input" type="number" min="4" max="10" value={(*input_columns).to_string()} oninput={on_cols_change} />
+                </div>
+                <div>
+                    {"Opponent: "}
+                    { for [("Computer", "Computer"), ("Human", "Human (same device)"), ("Online", "Online")].into_iter().map(|(opponent, label)| html! {
+                        <>
+                            <input type="radio" id={format!("ot_opponent_{}", opponent)} name="ot_opponent" value={opponent}
+                                   checked={*input_opponent == opponent}
+                                   onchange={on_opponent_change.clone()}/>
+                            <label for={format!("ot_opponent_{}", opponent)}>{label}</label>
+                        </>
+                    })}
+                </div>
+                { if *input_opponent == "Human" {
+                    html! {
+                        <div>
+                            { for (0..2).map(|player| html! {
+                                <input type="text" placeholder={format!("Player {}", player + 1)}
+                                       value={input_names[player].clone()}
+                                       oninput={on_name_change.reform(move |e: InputEvent| (player, e))} />
+                            })}
+                        </div>
+                    }
+                } else if *input_opponent == "Online" {
+                    html! {
+                        <div>
+                            <label for="ot_server_input">{"Game server:"}</label>
+                            <input id="ot_server_input" type="text" value={(*input_server).clone()} oninput={on_server_change} />
+                            <label for="ot_room_input">{"Room code:"}</label>
+                            <input id="ot_room_input" type="text" placeholder="Blank for a new room"
+                                   value={(*input_room).clone()} oninput={on_room_change} />
+                            <label for="ot_online_name_input">{"Your name:"}</label>
+                            <input id="ot_online_name_input" type="text" placeholder="Player"
+                                   value={input_names[0].clone()}
+                                   oninput={on_name_change.reform(|e: InputEvent| (0, e))} />
+                        </div>
+                    }
+                } else {
+                    html! {}
+                }}
+                <div>
+                    {if *input_opponent == "Human" { "Player 1 plays as: " } else { "Play as: " }}
+                    { for ["TOOT", "OTTO", "Random"].into_iter().map(|side| html! {
+                        <>
+                            <input type="radio" id={format!("side_{}", side)} name="side" value={side}
+                                   checked={*input_side == side}
+                                   onchange={on_side_change.clone()}/>
+                            <label for={format!("side_{}", side)}>{side}</label>
+                        </>
+                    })}
+                </div>
+                <div>
+                    {"First move: "}
+                    { for first_choices(&input_opponent).into_iter().map(|(first, label)| html! {
+                        <>
+                            <input type="radio" id={format!("ot_first_{}", first)} name="ot_first" value={first}
+                                   checked={*input_first == first}
+                                   onchange={on_first_change.clone()}/>
+                            <label for={format!("ot_first_{}", first)}>{label}</label>
+                        </>
+                    })}
+                </div>
+                <button type="submit">{"Start New Game"}</button>
+            </form>
             <div>
                 <input type="radio" name="difficulty_easy" value="Easy"
                        checked={*difficulty == "Easy"}
@@ -166,57 +607,110 @@ pub fn TootAndOttoBoard() -> Html {
                 <input type="radio" id="choose_t" name="player_choice" value="T"
                        checked={*player_choice == 'T'}
                        onchange={handle_option_change.clone()}
-                       disabled={player_t_pieces[0] == 0}/>
+                       disabled={board.letters_left(board.current_player(), 'T') == 0}/>
                 <label for="choose_t">{"Choose T"}</label>
 
                 <input type="radio" id="choose_o" name="player_choice" value="O"
                        checked={*player_choice == 'O'}
                        onchange={handle_option_change}
-                       disabled={player_o_pieces[0] == 0}/>
+                       disabled={board.letters_left(board.current_player(), 'O') == 0}/>
                 <label for="choose_o">{"Choose O"}</label>
             </div>
             <div class="post">
                 <br/>
-                <h4>{"Player Turn: "}{if *player_turn == 1 { "Player 1 (Red)" } else { "Player 2 (Yellow)" }}</h4>
+                <h4>{
+                    if names.is_some() {
+                        format!("{} spells {} and {} spells {}", player_name(USER, &names), board.word(USER).name(), player_name(COMPUTER, &names), board.word(COMPUTER).name())
+                    } else {
+                        format!("You are {} and the computer is {}", board.word(USER).name(), board.word(COMPUTER).name())
+                    }
+                }</h4>
+                { if let Some((code, player)) = &*online_seat {
+                    html! {
+                        <div>{format!("Room {}: share the code for someone to join. You are {}.", code, player_name(*player, &names))}</div>
+                    }
+                } else {
+                    html! {}
+                }}
+                { if let Some(status) = &*online_status {
+                    html! {<div>{status}</div>}
+                } else {
+                    html! {}
+                }}
+                <h4>{"Player Turn: "}{format!("{} ({})", player_name(board.current_player(), &names), if board.current_player() == USER { "Red" } else { "Yellow" })}</h4>
                 <small>{"Choose 'T' or 'O' to play."}</small>
                 <br/>
             </div>
             <div>
-                <div>{"User has "}
-                    {player_o_pieces[0]}{" 'O's left and "}
-                    {player_t_pieces[0]}{" 'T's left"}
-                </div>
-                <div>{"Computer has "}
-                    {player_o_pieces[1]}{" 'O's left and "}
-                    {player_t_pieces[1]}{" 'T's left"}
-                </div>
+                { for [USER, COMPUTER].into_iter().map(|player| html! {
+                    <div>{format!("{}: ", player_name(player, &names))}
+                        {board.letters_left(player, 'O')}{" 'O's left and "}
+                        {board.letters_left(player, 'T')}{" 'T's left"}
+                    </div>
+                })}
             </div>
             <div id="gameboard" class="w-[500px] border border-black bg-boardPrimaryBg">
-                { for (0..DEFAULT_OT_ROWS).map(|y| html! {
+                { for (0..board.rows()).map(|y| html! {
                     <div class="flex justify-center items-center gap-4 my-4">
-                        { for (0..DEFAULT_OT_COLS).map(|x| html! {
+                        { for (0..board.columns()).map(|x| html! {
                             <div onclick={handle_click.reform(move |_| x)}
                                  class={
                                     let base_class = "w-12 h-12 rounded-full flex items-center justify-center text-xl text-black";
-                                    let is_last_move = *last_move == Some((x, y));
+                                    let is_last_move = board.last_move() == Some((x, y));
                                     let animation_class = if is_last_move { "animate-drop" } else { "" };
-                                    let color_class = if board[y][x].1 == 1 {
+                                    let color_class = if board.cell(y, x).1 == 1 {
                                         "bg-chipPrimaryBg"
-                                    } else if board[y][x].1 == 2 {
+                                    } else if board.cell(y, x).1 == 2 {
                                         "bg-chipSecondaryBg"
                                     } else {
                                         "bg-white"
                                     };
                                     format!("{} {} {}", base_class, color_class, animation_class)
                                  }>
-                                { board[y][x].0.to_string() }
+                                { board.cell(y, x).0.to_string() }
                             </div>
                         })}
                     </div>
                 })}
             </div>
+            <div>
+                <button class="bg-violet-500 rounded-md p-2 text-white disabled:opacity-40"
+                    disabled={is_computer_thinking(&is_user_turn, &winner) || online.is_some() || take_back(&board, names.is_some()).is_none()}
+                    onclick={on_undo}>
+                    {"Undo"}
+                </button>
+                <button class="bg-violet-500 rounded-md p-2 text-white disabled:opacity-40"
+                    disabled={is_computer_thinking(&is_user_turn, &winner) || online.is_some() || redo.is_empty()}
+                    onclick={on_redo}>
+                    {"Redo"}
+                </button>
+            </div>
+            <div>
+                <button class="bg-violet-500 rounded-md p-2 text-white" onclick={on_export}>
+                    {"Show game record"}
+                </button>
+                <a class="bg-violet-500 rounded-md p-2 text-white" download="toot-otto.txt"
+                    href={format!("data:text/plain;charset=utf-8,{}", encode_uri_component(&game_record(&board, &names).to_string()))}>
+                    {"Download game record"}
+                </a>
+                <button class="bg-violet-500 rounded-md p-2 text-white disabled:opacity-40"
+                    disabled={online.is_some()}
+                    onclick={on_import}>
+                    {"Load game record"}
+                </button>
+                <div>
+                    <textarea class="w-[500px] h-40 border border-black font-mono"
+                        placeholder="Paste a game record here to load it"
+                        value={(*record_text).clone()} oninput={on_record_change} />
+                </div>
+                { if let Some(error) = &*record_error {
+                    html! {<div class="text-red-600">{error}</div>}
+                } else {
+                    html! {}
+                }}
+            </div>
             { if let Some(winner_player) = *winner {
-                popup_modal(winner_player)
+                popup_modal(&board, winner_player, &names, on_play_again)
             } else {
                 html! {}
             }}
@@ -225,358 +719,162 @@ pub fn TootAndOttoBoard() -> Html {
     }
 }
 
-fn check_winner(board: &Vec<Vec<(char, usize)>>) -> Option<usize> {
-    let toot_sequence = ['T', 'O', 'O', 'T'];
-    let otto_sequence = ['O', 'T', 'T', 'O'];
-    let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
-    let mut found_toot = false;
-    let mut found_otto = false;
-
-    for y in 0..DEFAULT_OT_ROWS {
-        for x in 0..DEFAULT_OT_COLS {
-            if board[y][x].0 != ' ' {
-                for &(dy, dx) in &directions {
-                    if check_sequence(board, x, y, dx, dy, &toot_sequence) {
-                        found_toot = true;
-                    }
-                    if check_sequence(board, x, y, dx, dy, &otto_sequence) {
-                        found_otto = true;
-                    }
-                }
-            }
-        }
-    }
-
-    match (found_toot, found_otto) {
-        (true, false) => Some(1), // Player 1 wins with TOOT
-        (false, true) => Some(2), // Player 2 wins with OTTO
-        (true, true) => Some(3),  // Both sequences formed, possible in rare scenarios
-        _ => None,
-    }
+/// The "First move" options and their labels, which name the players
+/// according to who the opponent is.
+fn first_choices(opponent: &str) -> [(&'static str, &'static str); 4] {
+    let (user, computer) = match opponent {
+        "Human" => ("Player 1", "Player 2"),
+        "Online" => ("You", "Your opponent"),
+        _ => ("You", "Computer"),
+    };
+    [
+        ("User", user),
+        ("Computer", computer),
+        ("Random", "Random"),
+        ("Alternate", "Take turns"),
+    ]
 }
 
-fn check_sequence(
-    board: &Vec<Vec<(char, usize)>>,
-    x: usize,
-    y: usize,
-    dx: isize,
-    dy: isize,
-    sequence: &[char],
-) -> bool {
-    for (index, &item) in sequence.iter().enumerate() {
-        let nx = x as isize + index as isize * dx;
-        let ny = y as isize + index as isize * dy;
-
-        if nx < 0
-            || nx >= DEFAULT_OT_COLS as isize
-            || ny < 0
-            || ny >= DEFAULT_OT_ROWS as isize
-            || board[ny as usize][nx as usize].0 != item
-        {
-            return false;
-        }
+/// How a player is shown: as you or the computer, or in a game between
+/// people by the name they gave.
+fn player_name(player: usize, names: &Option<Vec<String>>) -> String {
+    match names {
+        Some(names) if !names[player - 1].trim().is_empty() => names[player - 1].trim().to_string(),
+        Some(_) => format!("Player {}", player),
+        None if player == USER => "You".to_string(),
+        None => "Computer".to_string(),
     }
-    true
 }
 
-fn is_full_board(board: &Vec<Vec<(char, usize)>>) -> bool {
-    board.iter().all(|row| row.iter().all(|(c, _)| *c != ' '))
+/// Who places the first letter under the "First move" setting, given who
+/// did last game, if there was one.
+fn first_player(choice: &str, last_first: Option<usize>) -> usize {
+    match choice {
+        "Computer" => COMPUTER,
+        "Random" if rand::thread_rng().gen_bool(0.5) => COMPUTER,
+        "Alternate" if last_first == Some(USER) => COMPUTER,
+        _ => USER,
+    }
 }
 
-fn check_sequence_score(
-    board: &Vec<Vec<(char, usize)>>,
-    x: usize,
-    y: usize,
-    dx: isize,
-    dy: isize,
-    sequence: &[char],
-    piece: char,
-    win_score: isize,
-    block_score: isize,
-    advance_score: isize,
-    block_advance_score: isize,
-) -> isize {
-    let mut score = 0;
-    let mut match_count = 0;
-    let mut empty_count = 0;
-    let mut opponent_count = 0;
-
-    for (index, &char) in sequence.iter().enumerate() {
-        let nx = x as isize + index as isize * dx;
-        let ny = y as isize + index as isize * dy;
-
-        if nx < 0 || nx >= DEFAULT_OT_COLS as isize || ny < 0 || ny >= DEFAULT_OT_ROWS as isize {
-            continue;
+/// Puts a fresh board in place, and lets the computer open if it goes
+/// first. `computer` is its difficulty, or `None` in a hot-seat game.
+/// Returns the computer's opening move if it is still to come.
+fn start_game(
+    new_board: TootOttoPosition,
+    computer: Option<String>,
+    board: UseStateHandle<TootOttoPosition>,
+    player_choice: UseStateHandle<char>,
+    winner: UseStateHandle<Option<usize>>,
+    is_user_turn: UseStateHandle<bool>,
+    redo: UseStateHandle<Vec<(usize, char)>>,
+) -> Option<Timeout> {
+    board.set(new_board.clone());
+    player_choice.set('T');
+    winner.set(None);
+    redo.set(Vec::new());
+    match computer {
+        Some(difficulty) if new_board.current_player() == COMPUTER => {
+            Some(play_computer_turn(new_board, difficulty, board, winner, is_user_turn))
         }
-
-        if board[ny as usize][nx as usize].0 == char {
-            match_count += 1;
-        } else if board[ny as usize][nx as usize].0 == ' ' {
-            empty_count += 1;
-        } else {
-            opponent_count += 1;
+        _ => {
+            is_user_turn.set(true);
+            None
         }
     }
-
-    if opponent_count == 4 {
-        score -= win_score * 2;
-    } else {
-        match match_count {
-            4 => {
-                score += win_score;
-            }
-            3 => {
-                // OTT_ (Good) vs. OTTT (Bad)
-                if empty_count == 1 {
-                    score += advance_score; // Increase score for winning sequences
-                } else {
-                    score -= block_score;
-                }
-            }
-            2 => {
-                // OT__, _TT_, O__O, __TO
-                if empty_count == 2 {
-                    score += advance_score; // Increase score for winning sequences
-                } else if empty_count == 1 && opponent_count == 1 {
-                    // OTO_
-                    score -= block_advance_score * 4;
-                } else {
-                    // OTTT, OOTO
-                    score -= block_advance_score * 4;
-                }
-            }
-            1 => {
-                // _T__, __T_, T___, T__T
-                if empty_count == 3 {
-                    score += advance_score;
-                } else if empty_count == 2 && opponent_count == 1 {
-                    // _TT_, T_TO, TO_T
-                    score -= block_advance_score * 2;
-                } else if empty_count == 1 && opponent_count == 3 {
-                    // TTT_, _TTT, T_TT
-                    score -= block_score;
-                } else if empty_count == 0 && opponent_count == 3 {
-                    score += block_score;
-                }
-            }
-            0 => {
-                if empty_count == 4 {
-                    score += advance_score;
-                } else if opponent_count == 3 {
-                    // TTT_, _TTT, T_TT
-                    score -= block_score;
-                } else if opponent_count == 4 {
-                    score -= win_score * 3;
-                }
-            }
-            _ => {}
-        }
-    }
-
-    score
 }
 
-fn evaluate_board(board: &Vec<Vec<(char, usize)>>, piece: char) -> isize {
-    let mut score = 0;
-
-    const WIN_SCORE: isize = 100;
-    const BLOCK_SCORE: isize = 90; // Increased block score
-    const ADVANCE_SCORE: isize = 10;
-    const BLOCK_ADVANCE_SCORE: isize = 10; // Increased block advance score
-
-    let otto = ['O', 'T', 'T', 'O'];
-    let directions = [(0, 1), (1, 0), (1, 1), (1, -1), (-1, 1)];
-
-    for y in 0..DEFAULT_OT_ROWS {
-        for x in 0..DEFAULT_OT_COLS {
-            for &(dy, dx) in &directions {
-                let otto_score = check_sequence_score(
-                    board,
-                    x,
-                    y,
-                    dx,
-                    dy,
-                    &otto,
-                    piece,
-                    WIN_SCORE,
-                    BLOCK_SCORE,
-                    ADVANCE_SCORE,
-                    BLOCK_ADVANCE_SCORE,
-                );
-                score += otto_score;
-            }
+/// Places the computer's letter after a short pause, then hands the turn back
+/// to the user. Dropping the returned timeout calls the move off.
+fn play_computer_turn(
+    mut new_board: TootOttoPosition,
+    difficulty: String,
+    board: UseStateHandle<TootOttoPosition>,
+    winner: UseStateHandle<Option<usize>>,
+    is_user_turn: UseStateHandle<bool>,
+) -> Timeout {
+    is_user_turn.set(false);
+    Timeout::new(500, move || {
+        let computer_move = if difficulty == "Hard" {
+            best_move(&new_board)
+        } else {
+            random_move(&new_board)
+        };
+        if let Some((col, letter)) = computer_move {
+            new_board.play(col, letter);
         }
-    }
-    score
-}
-
-fn negamax(
-    board: &Vec<Vec<(char, usize)>>,
-    depth: usize,
-    alpha: isize,
-    beta: isize,
-    current_player: usize,
-    player_turn: usize,
-    piece: char,
-) -> (usize, isize) {
-    if depth == 0 || check_winner(board).is_some() {
-        let score = evaluate_board(board, piece);
-        return (
-            usize::MAX,
-            if player_turn == current_player {
-                score
-            } else {
-                -score
-            },
-        );
-    }
-
-    let mut alpha = alpha;
-    let mut best_value = isize::MIN;
-    let mut best_col = usize::MAX;
-
-    for col in 0..DEFAULT_OT_COLS {
-        if let Some(row) = (0..DEFAULT_OT_ROWS).rev().find(|&r| board[r][col].0 == ' ') {
-            let mut new_board = board.clone();
-            new_board[row][col] = (piece, player_turn);
-            for &next_piece in &['T', 'O'] {
-                let (_, value) = negamax(
-                    &new_board,
-                    depth - 1,
-                    beta.wrapping_neg(),
-                    alpha.wrapping_neg(),
-                    3 - current_player,
-                    player_turn,
-                    next_piece,
-                );
-
-                let value = -value;
-                if value >= best_value {
-                    best_value = value;
-                    best_col = col; // Update best_col here
-                }
-                alpha = alpha.max(value);
-                if alpha >= beta {
-                    break; // Beta cut-off
-                }
-            }
+        if let Some(win_player) = new_board.winner() {
+            winner.set(Some(win_player));
+        } else if new_board.is_draw() {
+            winner.set(Some(3));
         }
-    }
+        board.set(new_board);
+        is_user_turn.set(true);
+    })
+}
 
-    (best_col, best_value)
+/// Whether the computer's reply is still to come, so the board must not be
+/// changed under it.
+fn is_computer_thinking(
+    is_user_turn: &UseStateHandle<bool>,
+    winner: &UseStateHandle<Option<usize>>,
+) -> bool {
+    !**is_user_turn && winner.is_none()
 }
 
-fn make_computer_move(
-    board: &mut Vec<Vec<(char, usize)>>,
-    player_turn: usize,
-    player_t_pieces: &mut Vec<i32>,
-    player_o_pieces: &mut Vec<i32>,
-) {
-    let mut best_col = usize::MAX;
-    let mut best_value = isize::MIN;
-    let mut best_piece = 'T';
-    if player_t_pieces[1] > 0 && player_o_pieces[1] > 0 {
-        for &current_piece in &['T', 'O'] {
-            let (col, value) = negamax(
-                board,
-                5,
-                isize::MIN,
-                isize::MAX,
-                player_turn,
-                player_turn,
-                current_piece,
-            );
-            if value > best_value && col < DEFAULT_OT_COLS {
-                best_value = value;
-                best_col = col;
-                best_piece = current_piece;
-            }
+/// The board from before the user's last letter, or just the last letter in
+/// a hot-seat game, and the letters taken back to get there, latest first.
+/// `None` if the user hasn't played yet.
+fn take_back(
+    board: &TootOttoPosition,
+    is_hot_seat: bool,
+) -> Option<(TootOttoPosition, Vec<(usize, char)>)> {
+    let mut board = board.clone();
+    let mut undone = Vec::new();
+    while let Some(mv) = board.undo() {
+        undone.push(mv);
+        if is_hot_seat || board.current_player() == USER {
+            return Some((board, undone));
         }
-    } else if player_t_pieces[1] > 0 {
-        let (col, value) = negamax(
-            board,
-            5,
-            isize::MIN,
-            isize::MAX,
-            player_turn,
-            player_turn,
-            'T',
-        );
-        best_value = value;
-        best_col = col;
-        best_piece = 'T';
-    } else if player_o_pieces[1] > 0 {
-        let (col, value) = negamax(
-            board,
-            5,
-            isize::MIN,
-            isize::MAX,
-            player_turn,
-            player_turn,
-            'O',
-        );
-        best_value = value;
-        best_col = col;
-        best_piece = 'O';
     }
+    None
+}
 
-    if best_col <= DEFAULT_OT_COLS {
-        if let Some(row) = (0..DEFAULT_OT_ROWS)
-            .rev()
-            .find(|&r| board[r][best_col].0 == ' ')
-        {
-            if best_piece == 'T' {
-                if player_t_pieces[1] <= 0 {
-                    return;
-                }
-                player_t_pieces[1] -= 1;
-            } else {
-                if player_o_pieces[1] <= 0 {
-                    return;
-                }
-                player_o_pieces[1] -= 1;
-            }
-
-            board[row][best_col] = (best_piece, player_turn);
-            // Prevent computer from making player win
-            if check_winner(board) == Some(1) {
-                let opposite_piece = if best_piece == 'O' {
-                    if player_t_pieces[1] > 0 {
-                        'T'
-                    } else {
-                        'O'
-                    }
-                } else {
-                    if player_o_pieces[1] > 0 {
-                        'O'
-                    } else {
-                        'T'
-                    }
-                };
-                board[row][best_col] = (opposite_piece, player_turn);
-            }
-        }
-    }
+/// The game so far, for saving or sharing.
+fn game_record(board: &TootOttoPosition, names: &Option<Vec<String>>) -> TootOttoRecord {
+    let players = vec![player_name(USER, names), player_name(COMPUTER, names)];
+    TootOttoRecord::new(board, players, today())
 }
 
-fn popup_modal(winner: usize) -> Html {
+/// Announces the result, with `on_play_again` taking whether to swap who goes
+/// first in the next game.
+fn popup_modal(
+    board: &TootOttoPosition,
+    winner: usize,
+    names: &Option<Vec<String>>,
+    on_play_again: Callback<bool>,
+) -> Html {
     html! {
         <div class={"modal fixed z-1 left-0 top-0 w-full h-full overflow-auto bg-black bg-opacity-40"}>
             <div class={"modal-content bg-gray-100 mx-auto my-15 p-5 border border-gray-400 w-4/5"}>
                {
-                if winner == 1 {
-                    html! {<h3>{"Player 1 Wins!"}</h3>}
-                } else if winner == 2 {
-                    html! {<h3>{"Player 2 Wins!"}</h3>}
+                if winner == USER || winner == COMPUTER {
+                    let who = player_name(winner, names);
+                    html! {<h3>{format!("{} Wins! ({})", board.word(winner).name(), who)}</h3>}
                 } else {
                     html! {<h3>{"It's a Draw!"}</h3>}
                }
             }
-                <form>
-                    <button class="bg-violet-500 rounded-md p-2 text-white">
+                <div class="flex gap-2">
+                    <button class="bg-violet-500 rounded-md p-2 text-white"
+                        onclick={on_play_again.reform(|_| false)}>
                         {"Play Again"}
                     </button>
-                </form>
+                    <button class="bg-violet-500 rounded-md p-2 text-white"
+                        onclick={on_play_again.reform(|_| true)}>
+                        {"Play Again, Swapping Who Goes First"}
+                    </button>
+                </div>
             </div>
         </div>
     }
@@ -606,32 +904,3 @@ pub fn TootAndOttoRules() -> Html {
         </div>
     }
 }
-
-fn make_random_computer_move(
-    board: &mut Vec<Vec<(char, usize)>>,
-    player_t_pieces: &mut Vec<i32>,
-    player_o_pieces: &mut Vec<i32>,
-) {
-    let mut rng = rand::thread_rng();
-    let available_cols: Vec<usize> = (0..DEFAULT_OT_COLS)
-        .filter(|&col| board[0][col].0 == ' ')
-        .collect();
-
-    if let Some(&col) = available_cols.choose(&mut rng) {
-        if let Some(row) = (0..DEFAULT_OT_ROWS).rev().find(|&r| board[r][col].0 == ' ') {
-            let computer_choice = if rng.gen_bool(0.5) { 'T' } else { 'O' };
-            if computer_choice == 'T' {
-                if player_t_pieces[1] <= 0 {
-                    return;
-                }
-                player_t_pieces[1] -= 1;
-            } else {
-                if player_o_pieces[1] <= 0 {
-                    return;
-                }
-                player_o_pieces[1] -= 1;
-            }
-            board[row][col] = (computer_choice, 2);
-        }
-    }
-}
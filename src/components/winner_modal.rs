@@ -5,6 +5,12 @@ use yew::{function_component, html};
 #[derive(Properties, PartialEq)]
 pub struct WinnerModalProps {
     pub winner: usize,
+    /// Shown instead of "Player {winner}" when given.
+    #[prop_or_default]
+    pub winner_name: Option<AttrValue>,
+    /// Starts a new game with the same settings, passing whether to swap who
+    /// goes first.
+    pub on_play_again: Callback<bool>,
 }
 
 #[function_component]
@@ -19,15 +25,25 @@ pub fn WinnerModal(props: &WinnerModalProps) -> Html {
                         }
                     } else {
                         html! {
-                            <h3>{ format!("Player {} Wins!", props.winner)}</h3>
+                            <h3>{
+                                match &props.winner_name {
+                                    Some(name) => format!("{} Wins!", name),
+                                    None => format!("Player {} Wins!", props.winner),
+                                }
+                            }</h3>
                         }
                     }
                 }
-                <form>
-                    <button class="bg-violet-500 rounded-md p-2 text-white">
+                <div class="flex gap-2">
+                    <button class="bg-violet-500 rounded-md p-2 text-white"
+                        onclick={props.on_play_again.reform(|_| false)}>
                         {"Play Again"}
                     </button>
-                </form>
+                    <button class="bg-violet-500 rounded-md p-2 text-white"
+                        onclick={props.on_play_again.reform(|_| true)}>
+                        {"Play Again, Swapping Who Goes First"}
+                    </button>
+                </div>
             </div>
         </div>
     }
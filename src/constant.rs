@@ -1,9 +1,29 @@
+#[cfg(any(target_arch = "wasm32", feature = "check"))]
 pub const RED_BAR: &str = "bg-red-500 w-16 h-2 rounded-md";
+#[cfg(any(target_arch = "wasm32", feature = "check"))]
 pub const HEADER: &str = "text-4xl font-bold";
 pub const DEFAULT_C4_ROWS: usize = 6;
 pub const DEFAULT_C4_COLS: usize = 7;
 pub const DEFAULT_OT_ROWS: usize = 4;
 pub const DEFAULT_OT_COLS: usize = 6;
+pub const DEFAULT_OT_LETTERS: usize = 6;
 pub const USER: usize = 1;
 pub const COMPUTER: usize = 2;
 pub const EMPTY: usize = 0;
+pub const HARD_TIME_BUDGET_MS: u64 = 1000;
+pub const PERFECT_TIME_BUDGET_MS: u64 = 3000;
+/// The web board solves on the page's only thread, which can't redraw until
+/// the computer has moved, so it gives up on the solver much sooner.
+#[cfg(any(target_arch = "wasm32", feature = "check"))]
+pub const PERFECT_WEB_TIME_BUDGET_MS: u64 = 250;
+pub const DEFAULT_C4_WIN_LENGTH: usize = 4;
+/// Where `serve` listens, and where the web boards look for it by default.
+pub const DEFAULT_SERVER_PORT: u16 = 9001;
+pub const MAX_PLAYERS: usize = 4;
+#[cfg(any(target_arch = "wasm32", feature = "check"))]
+pub const CHIP_COLORS: [&str; MAX_PLAYERS] = [
+    "bg-chipPrimaryBg",
+    "bg-chipSecondaryBg",
+    "bg-chipTertiaryBg",
+    "bg-chipQuaternaryBg",
+];
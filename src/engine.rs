@@ -0,0 +1,9 @@
+// Only the command-line tool runs computer-versus-computer matches
+#[cfg(not(target_arch = "wasm32"))]
+pub mod arena;
+pub mod clock;
+pub mod connect4;
+pub mod online;
+pub mod record;
+pub mod transposition;
+pub mod tootandotto;
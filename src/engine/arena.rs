@@ -0,0 +1,560 @@
+use crate::constant::{COMPUTER, USER};
+use crate::engine::connect4::{search_with, solve, Connect4Position, Limit, Move, Weights};
+use crate::engine::tootandotto::{best_move_with, random_move, TootOttoPosition, TootOttoWeights};
+use rand::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// A two-player game that engines can play against each other.
+pub trait Game: Clone {
+    type Move: Copy;
+
+    fn current_player(&self) -> usize;
+    fn legal_moves(&self) -> Vec<Self::Move>;
+    /// Plays `mv` for the player to move, returning whether it was legal.
+    fn play(&mut self, mv: Self::Move) -> bool;
+    fn winner(&self) -> Option<usize>;
+    fn is_draw(&self) -> bool;
+}
+
+impl Game for Connect4Position {
+    type Move = Move;
+
+    fn current_player(&self) -> usize {
+        Connect4Position::current_player(self)
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        Connect4Position::legal_moves(self)
+    }
+
+    fn play(&mut self, mv: Move) -> bool {
+        Connect4Position::play(self, mv)
+    }
+
+    fn winner(&self) -> Option<usize> {
+        Connect4Position::winner(self)
+    }
+
+    fn is_draw(&self) -> bool {
+        Connect4Position::is_draw(self)
+    }
+}
+
+impl Game for TootOttoPosition {
+    type Move = (usize, char);
+
+    fn current_player(&self) -> usize {
+        TootOttoPosition::current_player(self)
+    }
+
+    fn legal_moves(&self) -> Vec<(usize, char)> {
+        TootOttoPosition::legal_moves(self)
+    }
+
+    fn play(&mut self, (col, letter): (usize, char)) -> bool {
+        TootOttoPosition::play(self, col, letter).is_some()
+    }
+
+    fn winner(&self) -> Option<usize> {
+        TootOttoPosition::winner(self)
+    }
+
+    fn is_draw(&self) -> bool {
+        TootOttoPosition::is_draw(self)
+    }
+}
+
+/// Something that picks moves in a game. New engines only have to implement
+/// this to take part in matches.
+pub trait Engine<G: Game> {
+    /// The move to play for the side to move, or `None` if there is none.
+    fn choose(&mut self, position: &G) -> Option<G::Move>;
+}
+
+/// The engines that come with the crate, as written on the command line:
+/// `random`, `minimax:DEPTH`, `timed:MS` or `perfect:MS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineSpec {
+    /// Any legal move.
+    Random,
+    /// Alpha-beta search to a fixed depth.
+    Minimax(usize),
+    /// Iterative deepening within a time budget, like Hard.
+    Timed(u64),
+    /// The Connect 4 solver, falling back to a timed search when it runs out
+    /// of time, like Perfect.
+    Perfect(u64),
+}
+
+impl EngineSpec {
+    /// TOOT-OTTO only has a fixed depth search, so the other levels have to
+    /// be turned down before a TOOT-OTTO match.
+    pub fn plays_toot_otto(&self) -> bool {
+        matches!(self, EngineSpec::Random | EngineSpec::Minimax(_))
+    }
+}
+
+impl FromStr for EngineSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match spec.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (spec, None),
+        };
+        let number = |what: &str| -> Result<u64, String> {
+            let value = value.ok_or_else(|| format!("{} needs a {}, e.g. {}:4", name, what, name))?;
+            match value.parse::<u64>() {
+                Ok(number) if number > 0 => Ok(number),
+                _ => Err(format!("'{}' is not a valid {}", value, what)),
+            }
+        };
+        match name {
+            "random" if value.is_none() => Ok(EngineSpec::Random),
+            "minimax" => Ok(EngineSpec::Minimax(number("depth")? as usize)),
+            "timed" => Ok(EngineSpec::Timed(number("time budget in milliseconds")?)),
+            "perfect" => Ok(EngineSpec::Perfect(number("time budget in milliseconds")?)),
+            _ => Err(format!(
+                "unknown engine '{}', expected random, minimax:DEPTH, timed:MS or perfect:MS",
+                spec
+            )),
+        }
+    }
+}
+
+impl fmt::Display for EngineSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineSpec::Random => write!(f, "random"),
+            EngineSpec::Minimax(depth) => write!(f, "minimax:{}", depth),
+            EngineSpec::Timed(budget_ms) => write!(f, "timed:{}", budget_ms),
+            EngineSpec::Perfect(budget_ms) => write!(f, "perfect:{}", budget_ms),
+        }
+    }
+}
+
+/// One of the engines that come with the crate, scoring positions with
+/// weights of its own. The weights of the game not being played are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tuned {
+    pub spec: EngineSpec,
+    pub connect4: Weights,
+    pub toot_otto: TootOttoWeights,
+}
+
+impl From<EngineSpec> for Tuned {
+    fn from(spec: EngineSpec) -> Self {
+        Tuned {
+            spec,
+            connect4: Weights::default(),
+            toot_otto: TootOttoWeights::default(),
+        }
+    }
+}
+
+impl Engine<Connect4Position> for Tuned {
+    fn choose(&mut self, position: &Connect4Position) -> Option<Move> {
+        if position.legal_moves().is_empty() {
+            return None;
+        }
+        let mut position = position.clone();
+        Some(match self.spec {
+            EngineSpec::Random => *position.legal_moves().choose(&mut rand::thread_rng())?,
+            EngineSpec::Minimax(depth) => {
+                search_with(&mut position, Limit::Depth(depth), self.connect4).0
+            }
+            EngineSpec::Timed(budget_ms) => {
+                search_with(&mut position, Limit::TimeMs(budget_ms), self.connect4).0
+            }
+            EngineSpec::Perfect(budget_ms) => match solve(&mut position, budget_ms) {
+                Some(solution) => Move::Drop(solution.column),
+                None => search_with(&mut position, Limit::TimeMs(budget_ms), self.connect4).0,
+            },
+        })
+    }
+}
+
+/// Only the levels [`EngineSpec::plays_toot_otto`] allows have a TOOT-OTTO
+/// search. The others panic rather than stand in for one.
+impl Engine<TootOttoPosition> for Tuned {
+    fn choose(&mut self, position: &TootOttoPosition) -> Option<(usize, char)> {
+        match self.spec {
+            EngineSpec::Random => random_move(position),
+            EngineSpec::Minimax(depth) => best_move_with(position, depth, &self.toot_otto),
+            EngineSpec::Timed(_) | EngineSpec::Perfect(_) => {
+                panic!("{} can't play TOOT-OTTO", self.spec)
+            }
+        }
+    }
+}
+
+impl Engine<Connect4Position> for EngineSpec {
+    fn choose(&mut self, position: &Connect4Position) -> Option<Move> {
+        Tuned::from(*self).choose(position)
+    }
+}
+
+impl Engine<TootOttoPosition> for EngineSpec {
+    fn choose(&mut self, position: &TootOttoPosition) -> Option<(usize, char)> {
+        Tuned::from(*self).choose(position)
+    }
+}
+
+/// Wins, draws and losses of the first engine in a match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+/// How many standard errors either side of the mean a 95% interval spans.
+const Z_95: f64 = 1.96;
+
+impl MatchResult {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The same games seen from the other engine.
+    pub fn reversed(&self) -> MatchResult {
+        MatchResult {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    /// Points per game, counting a draw as half a win.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /// A 95% confidence interval for the score. This is the Wilson score
+    /// interval, which stays sensible for clean sweeps, treating every game
+    /// as a coin toss. Draws make the true interval a little narrower.
+    pub fn score_interval(&self) -> (f64, f64) {
+        let games = self.games().max(1) as f64;
+        let score = self.score();
+        let z2 = Z_95 * Z_95;
+        let centre = (score + z2 / (2.0 * games)) / (1.0 + z2 / games);
+        let error = Z_95 / (1.0 + z2 / games)
+            * (score * (1.0 - score) / games + z2 / (4.0 * games * games)).sqrt();
+        ((centre - error).max(0.0), (centre + error).min(1.0))
+    }
+
+    /// The Elo rating difference the score implies, or `None` after a clean
+    /// sweep, which no finite difference explains.
+    pub fn elo_difference(&self) -> Option<f64> {
+        Some(elo_from_score(self.score())).filter(|elo| elo.is_finite())
+    }
+
+    /// The Elo rating differences at either end of the score's interval.
+    /// The far end of a clean sweep's is infinite.
+    pub fn elo_interval(&self) -> (f64, f64) {
+        let (low, high) = self.score_interval();
+        (elo_from_score(low), elo_from_score(high))
+    }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Plays `games` games between `first` and `second` and scores them for
+/// `first`. The engines swap seats every game so each starts half of them.
+/// The first `opening_moves` moves of every game are random, so that two
+/// deterministic engines don't play the same game over and over.
+/// `on_game` sees the running result after every game.
+pub fn play_match<G: Game>(
+    new_game: impl Fn() -> G,
+    first: &mut dyn Engine<G>,
+    second: &mut dyn Engine<G>,
+    games: usize,
+    opening_moves: usize,
+    mut on_game: impl FnMut(&MatchResult),
+) -> MatchResult {
+    let mut result = MatchResult::default();
+    for game in 0..games {
+        let first_seat = if game % 2 == 0 { USER } else { COMPUTER };
+        let mut position = new_game();
+        let mut moves = 0;
+        let winner = loop {
+            if let Some(winner) = position.winner() {
+                break Some(winner);
+            }
+            if position.is_draw() {
+                break None;
+            }
+            let mv = if moves < opening_moves {
+                position.legal_moves().choose(&mut rand::thread_rng()).copied()
+            } else if position.current_player() == first_seat {
+                first.choose(&position)
+            } else {
+                second.choose(&position)
+            };
+            // An engine with nothing to play ends the game without a winner
+            match mv {
+                Some(mv) if position.play(mv) => moves += 1,
+                _ => break None,
+            }
+        };
+        match winner {
+            Some(winner) if winner == first_seat => result.wins += 1,
+            Some(_) => result.losses += 1,
+            None => result.draws += 1,
+        }
+        on_game(&result);
+    }
+    result
+}
+
+/// Plays every engine against every other one, `games` games per pairing,
+/// as in [`play_match`]. Entry `[i][j]` of the result holds engine `i`'s
+/// games against engine `j`. `on_pairing` sees each pairing once it is
+/// over.
+pub fn round_robin<G: Game, E: Engine<G>>(
+    new_game: impl Fn() -> G,
+    engines: &mut [E],
+    games: usize,
+    opening_moves: usize,
+    mut on_pairing: impl FnMut(usize, usize, &MatchResult),
+) -> Vec<Vec<MatchResult>> {
+    let count = engines.len();
+    let mut results = vec![vec![MatchResult::default(); count]; count];
+    let pairings = (0..count).flat_map(|i| (i + 1..count).map(move |j| (i, j)));
+    for (i, j) in pairings {
+        let (left, right) = engines.split_at_mut(j);
+        let result = play_match(
+            &new_game,
+            &mut left[i],
+            &mut right[0],
+            games,
+            opening_moves,
+            |_| {},
+        );
+        results[i][j] = result;
+        results[j][i] = result.reversed();
+        on_pairing(i, j, &result);
+    }
+    results
+}
+
+/// Elo ratings that best explain a round robin's results, averaging zero.
+/// Each pairing counts one extra draw, which keeps ratings finite when an
+/// engine wins or loses every game.
+pub fn elo_ratings(results: &[Vec<MatchResult>]) -> Vec<f64> {
+    let count = results.len();
+    let mut ratings = vec![0.0; count];
+    let expected = |difference: f64| 1.0 / (1.0 + 10f64.powf(-difference / 400.0));
+    // Newton steps on each rating in turn converge in a few dozen rounds
+    for _ in 0..200 {
+        for i in 0..count {
+            let (mut surplus, mut slope) = (0.0, 0.0);
+            for j in (0..count).filter(|&j| j != i) {
+                let games = results[i][j].games() as f64 + 1.0;
+                let points = results[i][j].score() * (games - 1.0) + 0.5;
+                let p = expected(ratings[i] - ratings[j]);
+                surplus += points - games * p;
+                slope += games * p * (1.0 - p);
+            }
+            if slope > 0.0 {
+                ratings[i] += surplus / slope * 400.0 / 10f64.ln();
+            }
+        }
+        let mean = ratings.iter().sum::<f64>() / count.max(1) as f64;
+        ratings.iter_mut().for_each(|rating| *rating -= mean);
+    }
+    ratings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::tootandotto::TootOttoRules;
+
+    /// A game won by whoever moves first, so a match shows who sat where.
+    #[derive(Clone, Default)]
+    struct FirstMoveWins {
+        moves: usize,
+    }
+
+    impl Game for FirstMoveWins {
+        type Move = ();
+
+        fn current_player(&self) -> usize {
+            if self.moves == 0 {
+                USER
+            } else {
+                COMPUTER
+            }
+        }
+
+        fn legal_moves(&self) -> Vec<()> {
+            vec![()]
+        }
+
+        fn play(&mut self, _: ()) -> bool {
+            self.moves += 1;
+            true
+        }
+
+        fn winner(&self) -> Option<usize> {
+            (self.moves > 0).then_some(USER)
+        }
+
+        fn is_draw(&self) -> bool {
+            false
+        }
+    }
+
+    /// Plays the only move there is.
+    struct Pass;
+
+    impl Engine<FirstMoveWins> for Pass {
+        fn choose(&mut self, position: &FirstMoveWins) -> Option<()> {
+            position.legal_moves().first().copied()
+        }
+    }
+
+    fn result(wins: usize, draws: usize, losses: usize) -> MatchResult {
+        MatchResult {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn score_interval_is_the_wilson_interval() {
+        let (low, high) = result(5, 0, 5).score_interval();
+        assert_close(low, 0.2366);
+        assert_close(high, 0.7634);
+        // Draws count as half a win
+        assert_eq!(result(0, 10, 0).score_interval(), (low, high));
+
+        let (low, high) = result(10, 0, 0).score_interval();
+        assert_close(low, 0.7225);
+        assert_eq!(high, 1.0);
+        let (low, high) = result(0, 0, 10).score_interval();
+        assert_eq!(low, 0.0);
+        assert_close(high, 0.2775);
+    }
+
+    #[test]
+    fn elo_difference_follows_the_score() {
+        assert_eq!(result(2, 0, 2).elo_difference(), Some(0.0));
+        // Three points to one is 400 * log10(3) ahead
+        assert_close(result(3, 0, 1).elo_difference().unwrap(), 190.85);
+        assert_close(result(1, 0, 3).elo_difference().unwrap(), -190.85);
+        let (low, high) = result(3, 0, 1).elo_interval();
+        assert!(low < 0.0 && high > 190.85);
+    }
+
+    #[test]
+    fn clean_sweeps_have_no_finite_elo_difference() {
+        let sweep = result(10, 0, 0);
+        assert_eq!(sweep.elo_difference(), None);
+        assert_eq!(sweep.reversed().elo_difference(), None);
+        let (low, high) = sweep.elo_interval();
+        assert!(low.is_finite() && low > 0.0);
+        assert_eq!(high, f64::INFINITY);
+        let (low, high) = sweep.reversed().elo_interval();
+        assert_eq!(low, f64::NEG_INFINITY);
+        assert!(high.is_finite() && high < 0.0);
+    }
+
+    #[test]
+    fn engines_swap_seats_every_game() {
+        let mut played = Vec::new();
+        let outcome = play_match(
+            FirstMoveWins::default,
+            &mut Pass,
+            &mut Pass,
+            5,
+            0,
+            |result| played.push(*result),
+        );
+        // The first engine opens, and so wins, games 1, 3 and 5
+        assert_eq!(outcome, result(3, 0, 2));
+        assert_eq!(played.len(), 5);
+        assert_eq!(played[1], result(1, 0, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "timed:10 can't play TOOT-OTTO")]
+    fn timed_engines_refuse_toot_otto() {
+        let position = TootOttoPosition::new(TootOttoRules::default());
+        assert!(!EngineSpec::Timed(10).plays_toot_otto());
+        EngineSpec::Timed(10).choose(&position);
+    }
+
+    #[test]
+    fn round_robin_plays_every_pairing_once() {
+        let mut pairings = Vec::new();
+        let results = round_robin(
+            FirstMoveWins::default,
+            &mut [Pass, Pass, Pass, Pass],
+            2,
+            0,
+            |i, j, result| pairings.push((i, j, *result)),
+        );
+        assert_eq!(pairings.len(), 4 * 3 / 2);
+        assert_eq!(
+            pairings.iter().map(|&(i, j, _)| (i, j)).collect::<Vec<_>>(),
+            [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]
+        );
+        for (i, row) in results.iter().enumerate() {
+            for (j, result) in row.iter().enumerate() {
+                let games = if i == j { 0 } else { 2 };
+                assert_eq!(result.games(), games);
+                assert_eq!(*result, results[j][i].reversed());
+            }
+        }
+    }
+
+    #[test]
+    fn elo_ratings_fit_the_scores_with_a_draw_added() {
+        // 3.5 points out of 5 once the draw is added
+        let results = vec![
+            vec![MatchResult::default(), result(3, 0, 1)],
+            vec![result(1, 0, 3), MatchResult::default()],
+        ];
+        let ratings = elo_ratings(&results);
+        assert_close(ratings[0] + ratings[1], 0.0);
+        assert_close(ratings[0] - ratings[1], 400.0 * (3.5f64 / 1.5).log10());
+
+        // A clean sweep stays finite, at 4.5 points out of 5
+        let results = vec![
+            vec![MatchResult::default(), result(4, 0, 0)],
+            vec![result(0, 0, 4), MatchResult::default()],
+        ];
+        let ratings = elo_ratings(&results);
+        assert_close(ratings[0] - ratings[1], 400.0 * 9f64.log10());
+    }
+
+    #[test]
+    fn elo_ratings_put_the_stronger_engine_first() {
+        let even = result(5, 0, 5);
+        let ahead = result(8, 0, 2);
+        let results = vec![
+            vec![MatchResult::default(), ahead, ahead],
+            vec![ahead.reversed(), MatchResult::default(), even],
+            vec![ahead.reversed(), even, MatchResult::default()],
+        ];
+        let ratings = elo_ratings(&results);
+        assert!(ratings[0] > ratings[1]);
+        assert_close(ratings[1], ratings[2]);
+        assert_close(ratings.iter().sum::<f64>(), 0.0);
+    }
+}
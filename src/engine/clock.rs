@@ -0,0 +1,57 @@
+/// A wall-clock cut-off for searches. `std::time::Instant` panics on
+/// `wasm32-unknown-unknown`, so the browser build reads the JS clock instead.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    end_ms: f64,
+}
+
+impl Deadline {
+    pub fn after(budget_ms: u64) -> Self {
+        Deadline {
+            end_ms: now_ms() + budget_ms as f64,
+        }
+    }
+
+    pub fn has_passed(&self) -> bool {
+        now_ms() >= self.end_ms
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    web_sys::js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
+
+/// Today's date as YYYY-MM-DD, in UTC.
+#[cfg(target_arch = "wasm32")]
+pub fn today() -> String {
+    let date = String::from(web_sys::js_sys::Date::new_0().to_iso_string());
+    date[..10].to_string()
+}
+
+/// Today's date as YYYY-MM-DD, in UTC.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn today() -> String {
+    let days = (now_ms() / 86_400_000.0).floor() as i64;
+    // Days since 1970 to a civil date, counting years from March so the
+    // leap day comes last
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,1629 @@
+use crate::constant::{
+    DEFAULT_C4_COLS, DEFAULT_C4_ROWS, DEFAULT_C4_WIN_LENGTH, EMPTY, MAX_PLAYERS, USER,
+};
+use crate::engine::clock::Deadline;
+use crate::engine::transposition::{Bound, Entry, TranspositionTable};
+use rand::prelude::*;
+
+use std::cmp::{max, min, Reverse};
+use std::iter::successors;
+use std::sync::OnceLock;
+
+/// Largest board the bitboard can hold: every column needs `rows + 1` bits
+/// (one spare sentinel bit on top), so a 10x10 board uses 110 of 128 bits.
+pub const MAX_ROWS: usize = 10;
+pub const MAX_COLUMNS: usize = 10;
+pub const MIN_WIN_LENGTH: usize = 3;
+
+type Bitboard = u128;
+
+/// log2 of the number of transposition table slots used per search.
+const TABLE_SIZE_LOG2: u32 = 17;
+
+/// Iterative deepening limit for the variants with pops, whose games can go
+/// on forever.
+const MAX_POP_DEPTH: usize = 64;
+
+/// Bits from here up are never cells, so their Zobrist keys are free to mark
+/// the rest of the game state.
+const STATE_KEYS: usize = MAX_COLUMNS * (MAX_ROWS + 1);
+
+/// How many discs a player has to collect to win Pop 10.
+pub const POP_TEN_TARGET: usize = 10;
+
+/// One random key per (player, bit) pair. Seeded so hashes are reproducible
+/// between runs.
+fn zobrist_keys() -> &'static [[u64; 128]; MAX_PLAYERS] {
+    static KEYS: OnceLock<[[u64; 128]; MAX_PLAYERS]> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut rng = StdRng::seed_from_u64(0xC0FFEE);
+        let mut keys = [[0; 128]; MAX_PLAYERS];
+        for player_keys in keys.iter_mut() {
+            for key in player_keys.iter_mut() {
+                *key = rng.gen();
+            }
+        }
+        keys
+    })
+}
+
+/// A move for the player to turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+    /// Drop a disc into the column.
+    Drop(usize),
+    /// PopOut and Pop 10 only: remove your own disc from the bottom of the
+    /// column, letting the discs above it fall one row.
+    Pop(usize),
+}
+
+impl Move {
+    pub fn column(&self) -> usize {
+        match *self {
+            Move::Drop(col) | Move::Pop(col) => col,
+        }
+    }
+
+    /// A compact encoding for the transposition table.
+    fn index(&self) -> usize {
+        match *self {
+            Move::Drop(col) => col,
+            Move::Pop(col) => MAX_COLUMNS + col,
+        }
+    }
+
+    fn from_index(index: usize) -> Option<Move> {
+        match index {
+            col if col < MAX_COLUMNS => Some(Move::Drop(col)),
+            col if col < 2 * MAX_COLUMNS => Some(Move::Pop(col - MAX_COLUMNS)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Standard,
+    /// Players may pop one of their own discs out of the bottom row instead
+    /// of dropping one. A full board is not a draw; the game is drawn once
+    /// the same position comes up for the third time.
+    PopOut,
+    /// Played to five in a row on a 9x6 board whose two outer columns start
+    /// out filled with discs of alternating colours.
+    FiveInARow,
+    /// The board is first filled row by row. After that players take turns
+    /// popping one of their own discs from the bottom row: a disc that was
+    /// part of a line of four is kept and the player goes again, any other
+    /// disc has to be dropped back in. The first to collect ten discs wins.
+    PopTen,
+}
+
+impl Variant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::PopOut => "PopOut",
+            Variant::FiveInARow => "Five-in-a-Row",
+            Variant::PopTen => "Pop 10",
+        }
+    }
+
+    /// The board a variant has to be played on, if it has one of its own.
+    pub fn fixed_rules(&self) -> Option<Rules> {
+        match self {
+            Variant::Standard | Variant::PopOut => None,
+            Variant::FiveInARow => Some(Rules {
+                rows: 6,
+                columns: 9,
+                win_length: 5,
+                variant: Variant::FiveInARow,
+                topology: Topology::Flat,
+                players: 2,
+            }),
+            Variant::PopTen => Some(Rules {
+                variant: Variant::PopTen,
+                ..Rules::default()
+            }),
+        }
+    }
+}
+
+/// How the edges of the board connect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    Flat,
+    /// The left and right edges are joined, so horizontal and diagonal
+    /// lines can carry on across them.
+    Cylinder,
+}
+
+/// Who makes the moves for a player. Not part of the rules: the engine
+/// itself never looks at it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Seat {
+    Human,
+    Computer,
+}
+
+/// Everything that decides how a game is played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub rows: usize,
+    pub columns: usize,
+    /// How many discs in a row win the game.
+    pub win_length: usize,
+    pub variant: Variant,
+    pub topology: Topology,
+    /// Players take turns in order, starting with player 1 (`USER`).
+    pub players: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            rows: DEFAULT_C4_ROWS,
+            columns: DEFAULT_C4_COLS,
+            win_length: DEFAULT_C4_WIN_LENGTH,
+            variant: Variant::Standard,
+            topology: Topology::Flat,
+            players: 2,
+        }
+    }
+}
+
+/// Everything `undo` has to put back besides the discs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Snapshot {
+    hash: u64,
+    player: usize,
+    must_drop: bool,
+    collected: [usize; MAX_PLAYERS],
+}
+
+/// A Connect 4 game position, independent of any UI. Despite the name the
+/// number of discs in a row needed to win is configurable.
+///
+/// Discs are stored as one bitboard per player. Bit `col * (rows + 1) + h`
+/// is the disc `h` cells above the bottom of `col`; the extra bit per column
+/// stays empty so that shifted masks never bleed from one column into the
+/// next. Row 0 is the top of the board, matching how the board is rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Connect4Position {
+    rows: usize,
+    columns: usize,
+    win_length: usize,
+    variant: Variant,
+    topology: Topology,
+    players: usize,
+    pieces: [Bitboard; MAX_PLAYERS],
+    heights: [usize; MAX_COLUMNS],
+    moves: Vec<Move>,
+    /// The state before each move in `moves`, for undoing moves and
+    /// spotting repetitions.
+    history: Vec<Snapshot>,
+    windows: Vec<Bitboard>,
+    /// Zobrist hash of the discs alone.
+    hash: u64,
+    player: usize,
+    /// Pop 10: the player to move popped a disc that has to be dropped back.
+    must_drop: bool,
+    /// Pop 10: discs kept by each player.
+    collected: [usize; MAX_PLAYERS],
+}
+
+impl Connect4Position {
+    pub fn new(rules: Rules) -> Self {
+        let Rules {
+            rows,
+            columns,
+            win_length,
+            variant,
+            topology,
+            players,
+        } = rules;
+        assert!(
+            (4..=MAX_ROWS).contains(&rows) && (4..=MAX_COLUMNS).contains(&columns),
+            "board must be between 4x4 and {}x{}",
+            MAX_ROWS,
+            MAX_COLUMNS
+        );
+        assert!(
+            (MIN_WIN_LENGTH..=rows.max(columns)).contains(&win_length),
+            "win length must be between {} and the longest side of the board",
+            MIN_WIN_LENGTH
+        );
+        assert!(
+            (2..=MAX_PLAYERS).contains(&players),
+            "between 2 and {} players can play",
+            MAX_PLAYERS
+        );
+        if let Some(fixed) = variant.fixed_rules() {
+            assert!(rules == fixed, "{} has a board of its own", variant.name());
+        }
+        let mut position = Connect4Position {
+            rows,
+            columns,
+            win_length,
+            variant,
+            topology,
+            players,
+            pieces: [0; MAX_PLAYERS],
+            heights: [0; MAX_COLUMNS],
+            moves: Vec::new(),
+            history: Vec::new(),
+            windows: Vec::new(),
+            hash: 0,
+            player: USER,
+            must_drop: false,
+            collected: [0; MAX_PLAYERS],
+        };
+        position.windows = position.window_masks();
+        if variant == Variant::FiveInARow {
+            // Opposite colours at the bottom of each side keep the setup fair
+            for height in 0..rows {
+                position.place(height % 2, 0);
+                position.place((height + 1) % 2, columns - 1);
+            }
+        }
+        position
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules {
+            rows: self.rows,
+            columns: self.columns,
+            win_length: self.win_length,
+            variant: self.variant,
+            topology: self.topology,
+            players: self.players,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// How many discs in a row win the game.
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    fn bit_index(&self, row: usize, col: usize) -> usize {
+        col * (self.rows + 1) + (self.rows - 1 - row)
+    }
+
+    fn bit(&self, row: usize, col: usize) -> Bitboard {
+        1 << self.bit_index(row, col)
+    }
+
+    /// Zobrist hash of the discs on the board, the player to move and, in
+    /// Pop 10, the discs collected so far. Pops mean the disc count alone no
+    /// longer says whose turn it is.
+    pub fn hash(&self) -> u64 {
+        let keys = zobrist_keys();
+        let mut hash = self.hash;
+        if self.player != USER {
+            hash ^= keys[self.player - 1][127];
+        }
+        if self.must_drop {
+            hash ^= keys[0][127];
+        }
+        for (player_keys, &count) in keys.iter().zip(&self.collected) {
+            if count > 0 {
+                hash ^= player_keys[STATE_KEYS + count];
+            }
+        }
+        hash
+    }
+
+    /// Hash of the discs in one column, so a pop can rehash only that column.
+    fn column_hash(&self, col: usize) -> u64 {
+        let mut hash = 0;
+        for (pieces, keys) in self.pieces.iter().zip(zobrist_keys()) {
+            let mut discs = pieces & self.column_mask(col);
+            while discs != 0 {
+                hash ^= keys[discs.trailing_zeros() as usize];
+                discs &= discs - 1;
+            }
+        }
+        hash
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> usize {
+        let bit = self.bit(row, col);
+        (1..=self.players)
+            .find(|&player| self.pieces[player - 1] & bit != 0)
+            .unwrap_or(EMPTY)
+    }
+
+    /// The player whose turn it is. Player 1 (`USER`) always opens, and in
+    /// Pop 10 a player can have several moves in a row.
+    pub fn current_player(&self) -> usize {
+        self.player
+    }
+
+    /// The player whose turn comes after `player`'s.
+    pub fn next_player(&self, player: usize) -> usize {
+        player % self.players + 1
+    }
+
+    /// Pop 10: whether the board is still being filled.
+    pub fn is_setup(&self) -> bool {
+        self.variant == Variant::PopTen && self.moves.len() < self.rows * self.columns
+    }
+
+    /// Pop 10: whether the player to move has to drop back the disc they
+    /// just popped.
+    pub fn must_drop(&self) -> bool {
+        self.must_drop
+    }
+
+    /// Pop 10: how many discs `player` has kept.
+    pub fn collected(&self, player: usize) -> usize {
+        self.collected[player - 1]
+    }
+
+    /// Every move played so far, oldest first.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// The `(col, row)` of the most recently dropped disc, if the last move
+    /// was a drop.
+    pub fn last_move(&self) -> Option<(usize, usize)> {
+        match self.moves.last()? {
+            Move::Drop(col) => Some((*col, self.rows - self.heights[*col])),
+            Move::Pop(_) => None,
+        }
+    }
+
+    pub fn next_open_row(&self, col: usize) -> Option<usize> {
+        if col >= self.columns || self.heights[col] == self.rows {
+            return None;
+        }
+        Some(self.rows - 1 - self.heights[col])
+    }
+
+    /// Whether the player to move may pop the bottom disc of `col`.
+    pub fn can_pop(&self, col: usize) -> bool {
+        let pops_allowed = match self.variant {
+            Variant::PopOut => true,
+            Variant::PopTen => !self.is_setup() && !self.must_drop,
+            Variant::Standard | Variant::FiveInARow => false,
+        };
+        pops_allowed
+            && col < self.columns
+            && self.heights[col] > 0
+            && self.cell(self.rows - 1, col) == self.current_player()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        match mv {
+            Move::Drop(col) => {
+                self.next_open_row(col).is_some()
+                    && match self.variant {
+                        // The board fills up one row at a time
+                        Variant::PopTen if self.is_setup() => {
+                            self.heights[..self.columns].iter().all(|&height| height >= self.heights[col])
+                        }
+                        Variant::PopTen => self.must_drop,
+                        _ => true,
+                    }
+            }
+            Move::Pop(col) => self.can_pop(col),
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let drops = (0..self.columns).map(Move::Drop);
+        let pops = (0..self.columns).map(Move::Pop);
+        drops.chain(pops).filter(|&mv| self.is_legal(mv)).collect()
+    }
+
+    /// Legal moves with the centre columns first, since they take part in
+    /// the most lines and tend to cause the earliest cut-offs. Drops come
+    /// before pops. On a cylinder this merely keeps the order stable.
+    fn ordered_moves(&self) -> Vec<Move> {
+        let mut moves = self.legal_moves();
+        moves.sort_by_key(|mv| (2 * mv.column()).abs_diff(self.columns - 1));
+        moves.sort_by_key(|mv| matches!(mv, Move::Pop(_)));
+        moves
+    }
+
+    /// Plays `mv` for the current player, returning whether it was legal.
+    pub fn play(&mut self, mv: Move) -> bool {
+        if !self.is_legal(mv) {
+            return false;
+        }
+        let player = self.player;
+        self.history.push(Snapshot {
+            hash: self.hash(),
+            player,
+            must_drop: self.must_drop,
+            collected: self.collected,
+        });
+        self.moves.push(mv);
+        match mv {
+            Move::Drop(col) => {
+                self.place(player - 1, col);
+                self.must_drop = false;
+                self.end_turn(self.next_player(player));
+            }
+            Move::Pop(col) => {
+                let bottom = 1 << (col * (self.rows + 1));
+                let keeps = self.variant == Variant::PopTen && self.is_in_line(bottom, self.pieces[player - 1]);
+                let mask = self.column_mask(col);
+                self.hash ^= self.column_hash(col);
+                for pieces in self.pieces.iter_mut() {
+                    let column = (*pieces & mask) >> 1 & mask;
+                    *pieces = (*pieces & !mask) | column;
+                }
+                self.hash ^= self.column_hash(col);
+                self.heights[col] -= 1;
+                if self.variant == Variant::PopOut {
+                    self.end_turn(self.next_player(player));
+                } else if keeps {
+                    self.collected[player - 1] += 1;
+                    self.end_turn(player);
+                } else {
+                    self.must_drop = true;
+                }
+            }
+        }
+        true
+    }
+
+    /// Hands the move to `next`. In Pop 10 players without a disc of their
+    /// own in the bottom row sit the turn out.
+    fn end_turn(&mut self, next: usize) {
+        self.player = next;
+        if self.variant != Variant::PopTen || self.is_setup() {
+            return;
+        }
+        for _ in 0..self.players {
+            if (0..self.columns).any(|col| self.can_pop(col)) {
+                return;
+            }
+            self.player = self.next_player(self.player);
+        }
+        // Nobody can move, which makes the game a draw
+        self.player = next;
+    }
+
+    /// Drops a disc for the player with index `player` without making a move.
+    fn place(&mut self, player: usize, col: usize) {
+        let index = col * (self.rows + 1) + self.heights[col];
+        self.pieces[player] |= 1 << index;
+        self.hash ^= zobrist_keys()[player][index];
+        self.heights[col] += 1;
+    }
+
+    /// Takes back the most recent move.
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        let snapshot = self.history.pop()?;
+        self.player = snapshot.player;
+        self.must_drop = snapshot.must_drop;
+        self.collected = snapshot.collected;
+        let player = self.player - 1;
+        match mv {
+            Move::Drop(col) => {
+                self.heights[col] -= 1;
+                let index = col * (self.rows + 1) + self.heights[col];
+                self.pieces[player] &= !(1 << index);
+                self.hash ^= zobrist_keys()[player][index];
+            }
+            Move::Pop(col) => {
+                let mask = self.column_mask(col);
+                self.hash ^= self.column_hash(col);
+                for pieces in self.pieces.iter_mut() {
+                    let column = (*pieces & mask) << 1 & mask;
+                    *pieces = (*pieces & !mask) | column;
+                }
+                self.pieces[player] |= 1 << (col * (self.rows + 1));
+                self.hash ^= self.column_hash(col);
+                self.heights[col] += 1;
+            }
+        }
+        Some(mv)
+    }
+
+    fn has_line(&self, pieces: Bitboard) -> bool {
+        if self.topology == Topology::Cylinder {
+            // Shifted masks would fall off the edge instead of wrapping
+            return self.windows.iter().any(|&window| window & !pieces == 0);
+        }
+        let height = self.rows + 1;
+        // Vertical, horizontal and both diagonals. A bit survives only if
+        // the next `win_length - 1` cells along the direction are set too.
+        [1, height, height + 1, height - 1].iter().any(|&shift| {
+            let line = (1..self.win_length).fold(pieces, |line, i| line & (pieces >> (i * shift)));
+            line != 0
+        })
+    }
+
+    /// Whether the disc at `bit` is part of a line of `pieces`.
+    fn is_in_line(&self, bit: Bitboard, pieces: Bitboard) -> bool {
+        self.windows.iter().any(|&window| window & bit != 0 && window & pieces == window)
+    }
+
+    /// The player with a line, or in Pop 10 the player who collected enough
+    /// discs, if any. A pop can complete lines for several players at once,
+    /// in which case the player who popped wins if they have one. Otherwise
+    /// the win goes to whoever of the others is next to move in turn order.
+    pub fn winner(&self) -> Option<usize> {
+        if self.variant == Variant::PopTen {
+            return (1..=self.players).find(|&player| self.collected(player) >= POP_TEN_TARGET);
+        }
+        let has_line = |player: usize| self.has_line(self.pieces[player - 1]);
+        let mut with_line = (1..=self.players).filter(|&player| has_line(player));
+        match (with_line.next(), with_line.next()) {
+            (Some(_), Some(_)) => {
+                let popper = self.history.last()?.player;
+                successors(Some(popper), |&player| Some(self.next_player(player)))
+                    .take(self.players)
+                    .find(|&player| has_line(player))
+            }
+            (winner, _) => winner,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.occupied().count_ones() as usize == self.rows * self.columns
+    }
+
+    /// Whether the current position has already come up twice before.
+    fn is_third_repetition(&self) -> bool {
+        let hash = self.hash();
+        self.history.iter().filter(|previous| previous.hash == hash).count() >= 2
+    }
+
+    pub fn is_draw(&self) -> bool {
+        if self.winner().is_some() {
+            return false;
+        }
+        match self.variant {
+            Variant::Standard | Variant::FiveInARow => self.is_full(),
+            Variant::PopOut | Variant::PopTen => {
+                self.is_third_repetition() || self.legal_moves().is_empty()
+            }
+        }
+    }
+
+    fn occupied(&self) -> Bitboard {
+        self.pieces.iter().fold(0, |occupied, pieces| occupied | pieces)
+    }
+
+    /// Every playable cell, leaving out the sentinel bit of each column.
+    fn board_mask(&self) -> Bitboard {
+        (0..self.columns).fold(0, |mask, col| mask | self.column_mask(col))
+    }
+
+    /// The cell each non-full column would take the next disc in.
+    fn playable_cells(&self) -> Bitboard {
+        let bottom = (0..self.columns).fold(0, |mask, col| mask | 1 << (col * (self.rows + 1)));
+        (self.occupied() + bottom) & self.board_mask()
+    }
+
+    /// Empty cells that would complete a line of four for `pieces`,
+    /// whether or not a disc can be dropped there yet.
+    fn winning_cells(&self, pieces: Bitboard) -> Bitboard {
+        // Vertical
+        let mut cells = (pieces << 1) & (pieces << 2) & (pieces << 3);
+        let height = self.rows + 1;
+        for shift in [height, height + 1, height - 1] {
+            let pairs = (pieces << shift) & (pieces << (2 * shift));
+            cells |= pairs & (pieces << (3 * shift));
+            cells |= pairs & (pieces >> shift);
+            let pairs = (pieces >> shift) & (pieces >> (2 * shift));
+            cells |= pairs & (pieces << shift);
+            cells |= pairs & (pieces >> (3 * shift));
+        }
+        cells & (self.board_mask() ^ self.occupied())
+    }
+
+    fn column_mask(&self, col: usize) -> Bitboard {
+        ((1 << self.rows) - 1) << (col * (self.rows + 1))
+    }
+
+    /// Every run of `win_length` cells a line could occupy, as bitboard masks.
+    /// On a cylinder, runs that cross the side edges are included.
+    fn window_masks(&self) -> Vec<Bitboard> {
+        let rows = self.rows as isize;
+        let columns = self.columns as isize;
+        let span = self.win_length as isize - 1;
+        let wraps = self.topology == Topology::Cylinder;
+        let mut windows = Vec::new();
+        for (dy, dx) in [(0, 1), (1, 0), (1, 1), (-1, 1)] {
+            // A line around the whole cylinder would meet itself
+            if wraps && dx != 0 && span >= columns {
+                continue;
+            }
+            for y in 0..rows {
+                for x in 0..columns {
+                    let (end_y, end_x) = (y + span * dy, x + span * dx);
+                    if end_y < 0 || end_y >= rows || (!wraps && end_x >= columns) {
+                        continue;
+                    }
+                    windows.push((0..=span).fold(0, |mask, i| {
+                        let col = (x + i * dx).rem_euclid(columns);
+                        mask | self.bit((y + i * dy) as usize, col as usize)
+                    }));
+                }
+            }
+        }
+        // A line as long as the circumference fits in the same cells
+        // whichever column it starts from
+        windows.sort_unstable();
+        windows.dedup();
+        windows
+    }
+}
+
+/// What the heuristic score is made of, so that searches can be tuned
+/// against each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weights {
+    /// Each own disc in the centre column of a flat board.
+    pub center: isize,
+    /// A window filled by one player, i.e. a line.
+    pub complete: isize,
+    /// A window one disc short of a line, with the rest empty.
+    pub one_short: isize,
+    /// A window two discs short of a line, with the rest empty.
+    pub two_short: isize,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            center: 10,
+            complete: 10000,
+            one_short: 500,
+            two_short: 50,
+        }
+    }
+}
+
+/// Heuristic score from `piece`'s point of view, treating every other
+/// player as one opponent.
+fn score_position(position: &Connect4Position, piece: usize, weights: &Weights) -> isize {
+    let own = position.pieces[piece - 1];
+    let opponents = position.occupied() & !own;
+
+    // Every column of a cylinder is as central as any other
+    let mut score = 0;
+    if position.topology == Topology::Flat {
+        let center_count = (own & position.column_mask(position.columns / 2)).count_ones() as isize;
+        score += center_count * weights.center;
+    }
+
+    // Pop 10: a collected disc is worth more than any line still on the board
+    let collected: usize = position.collected.iter().sum();
+    let own_collected = position.collected[piece - 1] as isize;
+    score += (2 * own_collected - collected as isize) * 100_000;
+
+    for &window in &position.windows {
+        let opponent_discs = opponents & window;
+        // Discs of two different opponents can never become a line
+        let single_opponent = opponent_discs == 0
+            || position.pieces.iter().any(|&pieces| pieces & window == opponent_discs);
+        if single_opponent {
+            score += evaluate_window(
+                position.win_length,
+                (own & window).count_ones() as usize,
+                opponent_discs.count_ones() as usize,
+                weights,
+            );
+        }
+    }
+
+    score
+}
+
+/// Score of a win before any move, far above anything the heuristic can
+/// reach. Finished games score `WIN_SCORE - moves` for a win by `player`,
+/// the negation for anyone else's win and zero for a draw, so the search
+/// prefers quicker wins and slower losses.
+const WIN_SCORE: isize = 1_000_000_000;
+
+fn terminal_score(position: &Connect4Position, player: usize) -> Option<isize> {
+    let moves = position.moves.len() as isize;
+    match position.winner() {
+        Some(winner) if winner == player => Some(WIN_SCORE - moves),
+        Some(_) => Some(moves - WIN_SCORE),
+        None if position.is_draw() => Some(0),
+        None => None,
+    }
+}
+
+/// Whether a score means the game is decided within the search horizon.
+/// Games with pops have no fixed length, so leave plenty of room for the
+/// move count.
+fn is_decisive(score: isize) -> bool {
+    score.abs() > WIN_SCORE / 2
+}
+
+struct Searcher {
+    table: TranspositionTable,
+    deadline: Deadline,
+    nodes: u64,
+    timed_out: bool,
+    /// The player the search is for, who maximises the score.
+    player: usize,
+    weights: Weights,
+}
+
+impl Searcher {
+    /// Alpha-beta search from the searching player's point of view. With
+    /// more than two players it is a paranoid search: every other player is
+    /// assumed to be out to minimise that score. Returns the best move for
+    /// the side to move along with its score.
+    fn minimax(
+        &mut self,
+        position: &mut Connect4Position,
+        depth: usize,
+        mut alpha: isize,
+        mut beta: isize,
+    ) -> (Option<Move>, isize) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) && self.deadline.has_passed() {
+            self.timed_out = true;
+        }
+        if self.timed_out {
+            return (None, 0);
+        }
+        if let Some(score) = terminal_score(position, self.player) {
+            return (None, score);
+        }
+        if depth == 0 {
+            return (None, score_position(position, self.player, &self.weights));
+        }
+
+        // Not simply alternating, since a Pop 10 player can move twice in a
+        // row and there may be several opponents
+        let is_maximizing = position.current_player() == self.player;
+        let (original_alpha, original_beta) = (alpha, beta);
+        let mut moves = position.ordered_moves();
+        if let Some(entry) = self.table.probe(position.hash()) {
+            if entry.depth >= depth {
+                let best_move = Move::from_index(entry.best_move);
+                match entry.bound {
+                    Bound::Exact => return (best_move, entry.score),
+                    Bound::Lower => alpha = max(alpha, entry.score),
+                    Bound::Upper => beta = min(beta, entry.score),
+                }
+                if alpha >= beta {
+                    return (best_move, entry.score);
+                }
+            }
+            // Try the move that was best last time first
+            if let Some(index) = moves.iter().position(|mv| mv.index() == entry.best_move) {
+                moves[..=index].rotate_right(1);
+            }
+        }
+
+        let mut best_move = None;
+        let mut value = if is_maximizing { isize::MIN } else { isize::MAX };
+        for mv in moves {
+            position.play(mv);
+            let new_score = self.minimax(position, depth - 1, alpha, beta).1;
+            position.undo();
+            if self.timed_out {
+                return (None, 0);
+            }
+            if is_maximizing {
+                if new_score > value {
+                    value = new_score;
+                    best_move = Some(mv);
+                }
+                alpha = max(alpha, value);
+            } else {
+                if new_score < value {
+                    value = new_score;
+                    best_move = Some(mv);
+                }
+                beta = min(beta, value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if value <= original_alpha {
+            Bound::Upper
+        } else if value >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(Entry {
+            key: position.hash(),
+            depth,
+            score: value,
+            bound,
+            best_move: best_move.map_or(usize::MAX, |mv| mv.index()),
+        });
+        (best_move, value)
+    }
+}
+
+/// Iterative deepening search for the side to move. Searches one ply deeper
+/// at a time until `time_budget_ms` runs out, a forced result is found or the
+/// whole game tree has been covered, and returns the move and score from the
+/// deepest search that finished. Scores are from the point of view of the
+/// player to move. The game must not be over.
+pub fn search(position: &mut Connect4Position, time_budget_ms: u64) -> (Move, isize) {
+    search_with(position, Limit::TimeMs(time_budget_ms), Weights::default())
+}
+
+/// When an iterative deepening search stops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// After searching this many plies deep.
+    Depth(usize),
+    /// Once this many milliseconds have passed.
+    TimeMs(u64),
+}
+
+/// The search behind [`search`], stopping at `limit` and with the heuristic
+/// score made of `weights` rather than the defaults.
+pub fn search_with(position: &mut Connect4Position, limit: Limit, weights: Weights) -> (Move, isize) {
+    let (deadline, depth_limit) = match limit {
+        Limit::Depth(depth) => (Deadline::after(u64::MAX), depth.max(1)),
+        Limit::TimeMs(budget_ms) => (Deadline::after(budget_ms), usize::MAX),
+    };
+    let mut searcher = Searcher {
+        table: TranspositionTable::new(TABLE_SIZE_LOG2),
+        deadline,
+        nodes: 0,
+        timed_out: false,
+        player: position.current_player(),
+        weights,
+    };
+    let max_depth = match position.variant {
+        Variant::Standard | Variant::FiveInARow => {
+            position.rows * position.columns - position.occupied().count_ones() as usize
+        }
+        // Pops mean the game has no fixed length
+        Variant::PopOut | Variant::PopTen => MAX_POP_DEPTH,
+    };
+
+    let mut best = (position.ordered_moves()[0], 0);
+    for depth in 1..=max_depth.min(depth_limit) {
+        let (best_move, score) = searcher.minimax(position, depth, isize::MIN, isize::MAX);
+        if searcher.timed_out {
+            break;
+        }
+        if let Some(best_move) = best_move {
+            best = (best_move, score);
+        }
+        if is_decisive(score) {
+            break;
+        }
+    }
+    best
+}
+
+impl Searcher {
+    /// Exact negamax for the side to move, which must not have a winning
+    /// move available. A win scores `(empty cells + 1) / 2` counted just
+    /// before the winning disc, so faster wins score higher. Scores are only
+    /// accurate inside `alpha..beta`.
+    fn solve_negamax(
+        &mut self,
+        position: &mut Connect4Position,
+        mut alpha: isize,
+        mut beta: isize,
+    ) -> isize {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) && self.deadline.has_passed() {
+            self.timed_out = true;
+        }
+        if self.timed_out {
+            return 0;
+        }
+
+        let cells = (position.rows * position.columns) as isize;
+        let discs = position.moves.len() as isize;
+        let player = position.current_player() - 1;
+        let opponent_wins = position.winning_cells(position.pieces[1 - player]);
+        let playable = position.playable_cells();
+        let forced = playable & opponent_wins;
+        let mut candidates = if forced != 0 {
+            if forced & (forced - 1) != 0 {
+                // Two threats at once cannot both be blocked
+                return -(cells - discs) / 2;
+            }
+            forced
+        } else {
+            playable
+        };
+        // Never play directly underneath an opponent's winning cell
+        candidates &= !(opponent_wins >> 1);
+        if candidates == 0 {
+            return -(cells - discs) / 2;
+        }
+        if discs >= cells - 2 {
+            return 0;
+        }
+
+        let lowest = -(cells - 2 - discs) / 2;
+        if alpha < lowest {
+            alpha = lowest;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        let mut highest = (cells - 1 - discs) / 2;
+        if let Some(entry) = self.table.probe(position.hash()) {
+            highest = entry.score;
+        }
+        if beta > highest {
+            beta = highest;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        // Prefer moves that leave the most winning cells of our own
+        let mut moves: Vec<(u32, usize)> = position
+            .ordered_moves()
+            .into_iter()
+            .map(|mv| mv.column())
+            .filter(|&col| candidates & position.column_mask(col) != 0)
+            .map(|col| {
+                let cell = playable & position.column_mask(col);
+                let threats = position.winning_cells(position.pieces[player] | cell);
+                (threats.count_ones(), col)
+            })
+            .collect();
+        moves.sort_by_key(|&(threats, _)| Reverse(threats));
+
+        for (_, col) in moves {
+            position.play(Move::Drop(col));
+            let score = -self.solve_negamax(position, -beta, -alpha);
+            position.undo();
+            if self.timed_out {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = max(alpha, score);
+        }
+
+        self.table.store(Entry {
+            key: position.hash(),
+            depth: 0,
+            score: alpha,
+            bound: Bound::Upper,
+            best_move: usize::MAX,
+        });
+        alpha
+    }
+}
+
+/// The result of solving a position with perfect play from both sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Solution {
+    /// The best column for the side to move.
+    pub column: usize,
+    /// Who wins with perfect play, or `None` for a draw.
+    pub winner: Option<usize>,
+    /// How many discs are played from here until the game ends.
+    pub moves_to_end: usize,
+}
+
+/// Solves a game still in progress exactly, searching every line to the end
+/// of the game. Returns `None` if that takes longer than `time_budget_ms`,
+/// which is typical for the first dozen or so moves of a 7x6 game, and for
+/// any game not played by two players to four in a row on a flat board
+/// with the standard rules.
+///
+/// This doesn't reuse the minimax behind [`search`], even with a window
+/// around the exact scores. That search stops at a depth limit and scores
+/// the positions there with the heuristic, and its table keeps those
+/// guesses, so a bound from it can't be trusted as exact. Its win scores
+/// also sit near `WIN_SCORE`, far from the small range of exact scores.
+/// Instead every candidate column is scored by an exact negamax of its own.
+/// It only counts how soon a game is won, so a score is bounded by the
+/// empty cells left. It skips moves that hand the opponent a win, and pins
+/// down each score with null-window probes.
+pub fn solve(position: &mut Connect4Position, time_budget_ms: u64) -> Option<Solution> {
+    // The threat detection below only knows about two players making lines
+    // of four by dropping discs on a flat board
+    if position.players != 2
+        || position.win_length != 4
+        || position.variant != Variant::Standard
+        || position.topology != Topology::Flat
+    {
+        return None;
+    }
+    let mut searcher = Searcher {
+        table: TranspositionTable::new(TABLE_SIZE_LOG2),
+        deadline: Deadline::after(time_budget_ms),
+        nodes: 0,
+        timed_out: false,
+        player: position.current_player(),
+        weights: Weights::default(),
+    };
+    let player = position.current_player();
+    let opponent = position.next_player(player);
+    let cells = position.rows * position.columns;
+
+    let mut best: Option<(isize, usize)> = None;
+    for col in position.ordered_moves().iter().map(Move::column) {
+        position.play(Move::Drop(col));
+        let discs = position.moves.len() as isize;
+        let opponent_wins = position.winning_cells(position.pieces[opponent - 1]);
+        let score = if position.winner().is_some() {
+            (cells as isize + 2 - discs) / 2
+        } else if position.is_full() {
+            0
+        } else if opponent_wins & position.playable_cells() != 0 {
+            -(cells as isize + 1 - discs) / 2
+        } else {
+            -searcher.solve_exact(position)
+        };
+        position.undo();
+        if searcher.timed_out {
+            return None;
+        }
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, col));
+        }
+    }
+
+    let (score, column) = best?;
+    let discs = position.moves.len();
+    // Turn a win score back into the number of discs on the board just
+    // before the winning one, which the winner always drops on their parity
+    let winning_disc = |score: usize, winner_parity: usize| {
+        let before = cells + 1 - 2 * score;
+        if before % 2 == winner_parity % 2 {
+            before + 1
+        } else {
+            before
+        }
+    };
+    let solution = match score {
+        0 => Solution {
+            column,
+            winner: None,
+            moves_to_end: cells - discs,
+        },
+        score if score > 0 => Solution {
+            column,
+            winner: Some(player),
+            moves_to_end: winning_disc(score as usize, discs) - discs,
+        },
+        score => Solution {
+            column,
+            winner: Some(opponent),
+            moves_to_end: winning_disc(-score as usize, discs + 1) - discs,
+        },
+    };
+    Some(solution)
+}
+
+impl Searcher {
+    /// Pins down the exact score of the side to move with a binary search
+    /// of null-window probes, which prune far more than one wide window.
+    fn solve_exact(&mut self, position: &mut Connect4Position) -> isize {
+        let cells = (position.rows * position.columns) as isize;
+        let discs = position.moves.len() as isize;
+        let mut low = -(cells - discs) / 2;
+        let mut high = (cells + 1 - discs) / 2;
+        while low < high && !self.timed_out {
+            let mut probe = low + (high - low) / 2;
+            if probe <= 0 && low / 2 < probe {
+                probe = low / 2;
+            } else if probe >= 0 && high / 2 > probe {
+                probe = high / 2;
+            }
+            let score = self.solve_negamax(position, probe, probe + 1);
+            if score <= probe {
+                high = score;
+            } else {
+                low = score;
+            }
+        }
+        low
+    }
+}
+
+fn evaluate_window(
+    win_length: usize,
+    count_piece: usize,
+    count_opp_piece: usize,
+    weights: &Weights,
+) -> isize {
+    // Only windows holding discs of a single colour can still become a line,
+    // and they are worth more the fewer cells are left to fill
+    match (count_piece, count_opp_piece) {
+        (0, 0) => 0,
+        (count, 0) => window_value(win_length - count, weights),
+        (0, count) => -window_value(win_length - count, weights),
+        _ => 0,
+    }
+}
+
+fn window_value(count_empty: usize, weights: &Weights) -> isize {
+    match count_empty {
+        0 => weights.complete,
+        1 => weights.one_short,
+        2 => weights.two_short,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::COMPUTER;
+
+    /// Drops a disc into each column in turn, columns counted from 0.
+    fn play_columns(position: &mut Connect4Position, columns: &str) {
+        for col in columns.chars() {
+            let col = col.to_digit(10).unwrap() as usize;
+            assert!(position.play(Move::Drop(col)), "column {} is full", col);
+        }
+    }
+
+    fn position_after(columns: &str) -> Connect4Position {
+        position_with(Rules::default(), columns)
+    }
+
+    /// Searches `depth` plies deep, so the move found doesn't depend on how
+    /// fast the machine is.
+    fn search_to_depth(position: &mut Connect4Position, depth: usize) -> (Move, isize) {
+        search_with(position, Limit::Depth(depth), Weights::default())
+    }
+
+    fn position_with(rules: Rules, columns: &str) -> Connect4Position {
+        let mut position = Connect4Position::new(rules);
+        play_columns(&mut position, columns);
+        position
+    }
+
+    #[test]
+    fn undo_takes_back_every_move() {
+        let start = Connect4Position::new(Rules::default());
+        let mut position = start.clone();
+        play_columns(&mut position, "3342516");
+        let middle = position.clone();
+        play_columns(&mut position, "0066");
+        for _ in 0..4 {
+            position.undo();
+        }
+        assert_eq!(position, middle);
+        assert_eq!(position.hash(), middle.hash());
+        while position.undo().is_some() {}
+        assert_eq!(position, start);
+    }
+
+    #[test]
+    fn undo_returns_the_move_taken_back() {
+        let mut position = position_after("25");
+        assert_eq!(position.undo(), Some(Move::Drop(5)));
+        assert_eq!(position.current_player(), COMPUTER);
+        assert_eq!(position.undo(), Some(Move::Drop(2)));
+        assert_eq!(position.undo(), None);
+    }
+
+    #[test]
+    fn horizontal_line_wins() {
+        let position = position_after("0011223");
+        assert_eq!(position.winner(), Some(USER));
+    }
+
+    #[test]
+    fn vertical_line_wins() {
+        let position = position_after("1213141");
+        assert_eq!(position.winner(), Some(USER));
+        assert_eq!(position_after("0616061").winner(), None);
+        assert_eq!(position_after("06160616").winner(), Some(COMPUTER));
+    }
+
+    #[test]
+    fn diagonal_lines_win() {
+        let rising = position_after("01122323363");
+        assert_eq!(rising.winner(), Some(USER));
+        let falling = position_after("65544343303");
+        assert_eq!(falling.winner(), Some(USER));
+    }
+
+    #[test]
+    fn popper_wins_when_a_pop_completes_both_lines() {
+        let mut position = Connect4Position::new(Rules {
+            variant: Variant::PopOut,
+            ..Rules::default()
+        });
+        // Player 2 pops from under player 1, and the fall finishes a row
+        // for each of them
+        play_columns(&mut position, "11223360006");
+        assert_eq!(position.winner(), None);
+        assert!(position.play(Move::Pop(0)));
+        assert_eq!(position.winner(), Some(COMPUTER));
+    }
+
+    #[test]
+    fn pop_completing_two_other_lines_goes_to_the_next_in_turn() {
+        let mut position = Connect4Position::new(Rules {
+            variant: Variant::PopOut,
+            players: 3,
+            ..Rules::default()
+        });
+        // Player 1 pops from under player 2, who sits under player 3, and
+        // both of their rows are finished by the fall
+        play_columns(&mut position, "011600622533");
+        assert_eq!(position.winner(), None);
+        assert!(position.play(Move::Pop(0)));
+        assert_eq!(position.winner(), Some(COMPUTER));
+    }
+
+    #[test]
+    fn three_in_a_row_is_not_a_win() {
+        let position = position_after("001122");
+        assert_eq!(position.winner(), None);
+        assert!(!position.is_draw());
+    }
+
+    #[test]
+    fn full_board_without_a_line_is_a_draw() {
+        let position = position_after("000000111111422222233333344444555556666665");
+        assert!(position.is_full());
+        assert_eq!(position.winner(), None);
+        assert!(position.is_draw());
+        assert!(position.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn full_columns_have_no_moves() {
+        let mut position = position_after("333333");
+        assert_eq!(position.next_open_row(3), None);
+        assert!(!position.is_legal(Move::Drop(3)));
+        let moves = position.legal_moves();
+        assert_eq!(moves.len(), 6);
+        assert!(!moves.contains(&Move::Drop(3)));
+        let before = position.clone();
+        assert!(!position.play(Move::Drop(3)));
+        assert_eq!(position, before);
+    }
+
+    /// A 5x4 board, small enough to solve from any position in a test.
+    fn small_position_after(columns: &str) -> Connect4Position {
+        let mut position = Connect4Position::new(Rules {
+            rows: 4,
+            columns: 5,
+            ..Rules::default()
+        });
+        play_columns(&mut position, columns);
+        position
+    }
+
+    #[test]
+    fn solve_takes_an_immediate_win() {
+        let mut position = small_position_after("010203");
+        let solution = solve(&mut position, u64::MAX).unwrap();
+        assert_eq!(
+            solution,
+            Solution {
+                column: 0,
+                winner: Some(USER),
+                moves_to_end: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn solve_finds_a_forced_win_in_three() {
+        // Dropping into column 3 threatens both ends of the bottom row
+        let mut position = small_position_after("1122");
+        let solution = solve(&mut position, u64::MAX).unwrap();
+        assert_eq!(
+            solution,
+            Solution {
+                column: 3,
+                winner: Some(USER),
+                moves_to_end: 3,
+            }
+        );
+        assert_eq!(position, small_position_after("1122"));
+    }
+
+    #[test]
+    fn solve_counts_moves_to_a_loss() {
+        let mut position = small_position_after("11223");
+        let solution = solve(&mut position, u64::MAX).unwrap();
+        assert_eq!(solution.winner, Some(USER));
+        assert_eq!(solution.moves_to_end, 2);
+    }
+
+    #[test]
+    fn solve_draws_the_last_disc() {
+        let mut position = position_after("00000011111142222223333334444455555666666");
+        let solution = solve(&mut position, u64::MAX).unwrap();
+        assert_eq!(
+            solution,
+            Solution {
+                column: 5,
+                winner: None,
+                moves_to_end: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn solve_gives_up_on_other_rules() {
+        let mut position = Connect4Position::new(Rules {
+            variant: Variant::PopOut,
+            ..Rules::default()
+        });
+        assert_eq!(solve(&mut position, u64::MAX), None);
+    }
+
+    #[test]
+    fn columns_off_the_board_are_illegal() {
+        let position = Connect4Position::new(Rules::default());
+        assert!(!position.is_legal(Move::Drop(7)));
+        assert!(!position.is_legal(Move::Pop(0)));
+        assert_eq!(
+            position.legal_moves(),
+            (0..7).map(Move::Drop).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn search_prefers_the_faster_win() {
+        // Column 6 wins at once, while column 4 threatens both ends of the
+        // bottom row and wins two moves later
+        let mut position = position_after("2032636062");
+        let mut searcher = Searcher {
+            table: TranspositionTable::new(TABLE_SIZE_LOG2),
+            deadline: Deadline::after(u64::MAX),
+            nodes: 0,
+            timed_out: false,
+            player: USER,
+            weights: Weights::default(),
+        };
+        assert_eq!(
+            searcher.minimax(&mut position, 5, isize::MIN, isize::MAX),
+            (Some(Move::Drop(6)), WIN_SCORE - 11)
+        );
+        assert_eq!(
+            search_to_depth(&mut position, 5),
+            (Move::Drop(6), WIN_SCORE - 11)
+        );
+    }
+
+    #[test]
+    fn search_scores_losses_and_draws_by_when_they_come() {
+        // Whatever player 2 blocks, player 1 wins with the 13th disc
+        let mut position = position_after("20326360624");
+        assert_eq!(search_to_depth(&mut position, 3).1, 13 - WIN_SCORE);
+        let mut position = position_after("00000011111142222223333334444455555666666");
+        assert_eq!(search_to_depth(&mut position, 3), (Move::Drop(5), 0));
+    }
+
+    #[test]
+    fn lines_of_other_lengths_win() {
+        let three = Rules {
+            win_length: 3,
+            ..Rules::default()
+        };
+        assert_eq!(position_with(three, "0011").winner(), None);
+        assert_eq!(position_with(three, "00112").winner(), Some(USER));
+        let five = Rules {
+            win_length: 5,
+            ..Rules::default()
+        };
+        assert_eq!(position_with(five, "0011223").winner(), None);
+        assert_eq!(position_with(five, "001122334").winner(), Some(USER));
+        // Four of player 2's discs stand in column 1
+        assert_eq!(position_with(five, "010101010").winner(), Some(USER));
+    }
+
+    #[test]
+    fn search_plays_to_the_win_length() {
+        let mut position = position_with(
+            Rules {
+                win_length: 3,
+                ..Rules::default()
+            },
+            "0011",
+        );
+        assert_eq!(
+            search_to_depth(&mut position, 2),
+            (Move::Drop(2), WIN_SCORE - 5)
+        );
+    }
+
+    #[test]
+    fn five_in_a_row_starts_with_the_outer_columns_filled() {
+        let mut position = Connect4Position::new(Variant::FiveInARow.fixed_rules().unwrap());
+        assert_eq!(
+            (position.rows(), position.columns(), position.win_length()),
+            (6, 9, 5)
+        );
+        // Alternating colours, opposite at the bottom of each side
+        assert_eq!(position.cell(5, 0), USER);
+        assert_eq!(position.cell(4, 0), COMPUTER);
+        assert_eq!(position.cell(5, 8), COMPUTER);
+        assert_eq!(position.cell(0, 8), USER);
+        assert!(position.moves().is_empty());
+        assert_eq!(position.current_player(), USER);
+        assert_eq!(
+            position.legal_moves(),
+            (1..8).map(Move::Drop).collect::<Vec<_>>()
+        );
+        // The setup discs count towards lines
+        play_columns(&mut position, "11223");
+        assert_eq!(position.winner(), None);
+        play_columns(&mut position, "34");
+        assert_eq!(position.winner(), Some(USER));
+        assert_eq!(position.undo(), Some(Move::Drop(4)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Five-in-a-Row has a board of its own")]
+    fn five_in_a_row_keeps_its_board() {
+        Connect4Position::new(Rules {
+            variant: Variant::FiveInARow,
+            ..Rules::default()
+        });
+    }
+
+    /// Plays moves written as columns counted from 0, with a `p` in front of
+    /// a pop, such as `"3 p0"`.
+    fn play_moves(position: &mut Connect4Position, moves: &str) {
+        for mv in moves.split_whitespace() {
+            let mv = match mv.strip_prefix('p') {
+                Some(col) => Move::Pop(col.parse().unwrap()),
+                None => Move::Drop(mv.parse().unwrap()),
+            };
+            assert!(position.play(mv), "{:?} is illegal", mv);
+        }
+    }
+
+    fn pop_ten_after(columns: &str) -> Connect4Position {
+        position_with(Variant::PopTen.fixed_rules().unwrap(), columns)
+    }
+
+    #[test]
+    fn pop_ten_fills_the_board_row_by_row() {
+        let mut position = pop_ten_after("3");
+        assert!(position.is_setup());
+        assert!(!position.is_legal(Move::Drop(3)));
+        assert_eq!(position.legal_moves().len(), 6);
+        play_columns(&mut position, "012456");
+        assert!(position.is_legal(Move::Drop(3)));
+        assert!(!position.is_legal(Move::Pop(3)));
+        play_columns(&mut position, &"0123456".repeat(5));
+        assert!(!position.is_setup());
+        assert_eq!(position.winner(), None);
+        assert!(!position.is_draw());
+    }
+
+    #[test]
+    fn pop_ten_keeps_discs_in_lines_and_returns_the_rest() {
+        // Every disc sits on a diagonal of its own colour, except that the
+        // bottom of column 4 loses its line once columns 0 and 2 drop
+        let mut position = pop_ten_after(&"0123456".repeat(6));
+        assert_eq!(position.current_player(), USER);
+        assert_eq!(
+            position.legal_moves(),
+            [Move::Pop(0), Move::Pop(2), Move::Pop(4), Move::Pop(6)]
+        );
+        play_moves(&mut position, "p0 p2");
+        assert_eq!(position.collected(USER), 2);
+        assert_eq!(position.current_player(), USER);
+
+        play_moves(&mut position, "p4");
+        assert_eq!(position.collected(USER), 2);
+        assert!(position.must_drop());
+        assert_eq!(position.current_player(), USER);
+        assert_eq!(
+            position.legal_moves(),
+            [Move::Drop(0), Move::Drop(2), Move::Drop(4)]
+        );
+        play_moves(&mut position, "0");
+        assert!(!position.must_drop());
+        assert_eq!(position.current_player(), COMPUTER);
+
+        position.undo();
+        position.undo();
+        assert!(!position.must_drop());
+        position.undo();
+        assert_eq!(position.collected(USER), 1);
+    }
+
+    #[test]
+    fn pop_ten_is_won_by_collecting_ten_discs() {
+        let mut position = pop_ten_after("354120606243151360254520136410325641645032");
+        play_moves(
+            &mut position,
+            "p6 p6 6 p5 p1 p0 p0 p0 p5 6 p4 p4 p4 p3 p1 p6 6 p5 5 p6 6 p6 p5 6 p6 6 p4 5 \
+             p6 6 p3 p3 p3 p6 6 p6 p6 p5",
+        );
+        assert_eq!(position.collected(USER), POP_TEN_TARGET - 1);
+        assert_eq!(position.winner(), None);
+        play_moves(&mut position, "p5");
+        assert_eq!(position.collected(USER), POP_TEN_TARGET);
+        assert_eq!(position.winner(), Some(USER));
+        assert!(!position.is_draw());
+    }
+
+    fn cylinder() -> Rules {
+        Rules {
+            topology: Topology::Cylinder,
+            ..Rules::default()
+        }
+    }
+
+    #[test]
+    fn horizontal_lines_wrap_around_a_cylinder() {
+        // Columns 5, 6, 0 and 1 along the bottom row
+        assert_eq!(position_with(cylinder(), "5566001").winner(), Some(USER));
+        assert_eq!(position_after("5566001").winner(), None);
+    }
+
+    #[test]
+    fn diagonal_lines_wrap_around_a_cylinder() {
+        // Rising from the bottom of column 5 to the fourth row of column 1
+        let mut position = position_with(cylinder(), "5660300111");
+        assert_eq!(position.winner(), None);
+        play_columns(&mut position, "1");
+        assert_eq!(position.winner(), Some(USER));
+        assert_eq!(position_after("56603001111").winner(), None);
+    }
+
+    #[test]
+    fn search_sees_lines_across_the_edge() {
+        let mut position = position_with(cylinder(), "556600");
+        let (mv, score) = search_to_depth(&mut position, 1);
+        assert!([Move::Drop(1), Move::Drop(4)].contains(&mv));
+        assert_eq!(score, WIN_SCORE - 7);
+        assert!(!is_decisive(
+            search_to_depth(&mut position_after("556600"), 1).1
+        ));
+    }
+
+    #[test]
+    fn paranoid_search_blocks_the_next_player() {
+        // Player 2 holds columns 1 to 3 of the bottom row, with column 0
+        // already taken, so player 1 has to fill column 4
+        let three = Rules {
+            players: 3,
+            ..Rules::default()
+        };
+        let mut position = position_with(three, "610520635");
+        assert_eq!(position.current_player(), USER);
+        assert_eq!(search_to_depth(&mut position, 3).0, Move::Drop(4));
+
+        let four = Rules {
+            players: 4,
+            ..Rules::default()
+        };
+        let mut position = position_with(four, "616052056356");
+        assert_eq!(position.current_player(), USER);
+        assert_eq!(search_to_depth(&mut position, 4).0, Move::Drop(4));
+    }
+}
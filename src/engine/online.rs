@@ -0,0 +1,322 @@
+//! Messages between the game server and the boards playing on it.
+//!
+//! Each WebSocket text message starts with a command word and its arguments
+//! on one line. The messages that carry a game follow that line with the
+//! game's record, so the server and the boards share one format with saved
+//! games.
+
+use crate::engine::connect4::Move;
+use crate::engine::record::{Connect4Record, TootOttoRecord};
+use std::fmt;
+use std::str::FromStr;
+
+/// The game played in a room, as far as it has got.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OnlineGame {
+    Connect4(Connect4Record),
+    TootOtto(TootOttoRecord),
+}
+
+impl OnlineGame {
+    /// One name per player, empty for a seat nobody has taken yet.
+    pub fn names(&self) -> &[String] {
+        match self {
+            OnlineGame::Connect4(record) => &record.names,
+            OnlineGame::TootOtto(record) => &record.names,
+        }
+    }
+}
+
+impl fmt::Display for OnlineGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnlineGame::Connect4(record) => record.fmt(f),
+            OnlineGame::TootOtto(record) => record.fmt(f),
+        }
+    }
+}
+
+impl FromStr for OnlineGame {
+    type Err = String;
+
+    /// Reads either kind of record, telling them apart by the `Game` header.
+    fn from_str(text: &str) -> Result<Self, String> {
+        match text.parse() {
+            Ok(record) => Ok(OnlineGame::Connect4(record)),
+            // Only a record that isn't Connect 4 at all is read as TOOT-OTTO
+            Err(error) if error.starts_with("not a ") => text.parse().map(OnlineGame::TootOtto),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// What a board asks of the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientMessage {
+    /// Opens a room for the game, which may already have moves, and takes
+    /// player 1's seat under the first name in the record.
+    Create(OnlineGame),
+    /// Takes the first free seat in the room with this code.
+    Join { code: String, name: String },
+    /// Takes back the seat held with `token` after the connection dropped.
+    Rejoin { code: String, token: String },
+    /// A Connect 4 move. Columns are counted from 0 here but from 1 in the
+    /// message, as in records.
+    Connect4Move(Move),
+    /// A letter dropped into a TOOT-OTTO column, counted like Connect 4's.
+    TootOttoMove(usize, char),
+    /// Starts a new game in the room once this one is over, handing the
+    /// first move to the next player if `swap_first` is set.
+    PlayAgain { swap_first: bool },
+}
+
+impl fmt::Display for ClientMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientMessage::Create(game) => write!(f, "create\n{}", game),
+            ClientMessage::Join { code, name } => write!(f, "join {} {}", code, name),
+            ClientMessage::Rejoin { code, token } => write!(f, "rejoin {} {}", code, token),
+            ClientMessage::Connect4Move(Move::Drop(col)) => write!(f, "drop {}", col + 1),
+            ClientMessage::Connect4Move(Move::Pop(col)) => write!(f, "pop {}", col + 1),
+            ClientMessage::TootOttoMove(col, letter) => write!(f, "place {} {}", col + 1, letter),
+            ClientMessage::PlayAgain { swap_first: false } => write!(f, "again"),
+            ClientMessage::PlayAgain { swap_first: true } => write!(f, "again swap"),
+        }
+    }
+}
+
+impl FromStr for ClientMessage {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let (command, args, body) = split_message(text);
+        let message = match (command, &args[..]) {
+            ("create", []) => ClientMessage::Create(body.parse()?),
+            // Names may have spaces, so the name is the rest of the line
+            ("join", [code, ..]) => ClientMessage::Join {
+                code: code.to_string(),
+                name: args[1..].join(" "),
+            },
+            ("rejoin", [code, token]) => ClientMessage::Rejoin {
+                code: code.to_string(),
+                token: token.to_string(),
+            },
+            ("drop", [col]) => ClientMessage::Connect4Move(Move::Drop(column(col)?)),
+            ("pop", [col]) => ClientMessage::Connect4Move(Move::Pop(column(col)?)),
+            ("place", [col, letter]) => match letter.to_uppercase().as_str() {
+                "T" => ClientMessage::TootOttoMove(column(col)?, 'T'),
+                "O" => ClientMessage::TootOttoMove(column(col)?, 'O'),
+                _ => return Err(format!("'{}' is not a letter of TOOT-OTTO", letter)),
+            },
+            ("again", []) => ClientMessage::PlayAgain { swap_first: false },
+            ("again", ["swap"]) => ClientMessage::PlayAgain { swap_first: true },
+            _ => {
+                return Err(format!(
+                    "'{}' is not a message",
+                    text.lines().next().unwrap_or_default()
+                ))
+            }
+        };
+        Ok(message)
+    }
+}
+
+/// What the server tells a board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerMessage {
+    /// The board holds `player`'s seat in the room with this code, and can
+    /// get it back with `token`. Sent again if the players change seats.
+    Joined {
+        code: String,
+        player: usize,
+        token: String,
+    },
+    /// The game in the room after every change, and how many seats are
+    /// still free.
+    State { game: OnlineGame, open_seats: usize },
+    /// Why the last request was turned down.
+    Error(String),
+}
+
+impl fmt::Display for ServerMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerMessage::Joined {
+                code,
+                player,
+                token,
+            } => write!(f, "joined {} {} {}", code, player, token),
+            ServerMessage::State { game, open_seats } => {
+                write!(f, "state {}\n{}", open_seats, game)
+            }
+            ServerMessage::Error(error) => write!(f, "error {}", error),
+        }
+    }
+}
+
+impl FromStr for ServerMessage {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let (command, args, body) = split_message(text);
+        let message = match (command, &args[..]) {
+            ("joined", [code, player, token]) => ServerMessage::Joined {
+                code: code.to_string(),
+                player: number(player)?,
+                token: token.to_string(),
+            },
+            ("state", [open_seats]) => ServerMessage::State {
+                game: body.parse()?,
+                open_seats: number(open_seats)?,
+            },
+            ("error", _) => ServerMessage::Error(args.join(" ")),
+            _ => {
+                return Err(format!(
+                    "'{}' is not a message",
+                    text.lines().next().unwrap_or_default()
+                ))
+            }
+        };
+        Ok(message)
+    }
+}
+
+/// The command word, its arguments, and the lines after the first.
+fn split_message(text: &str) -> (&str, Vec<&str>, &str) {
+    let (line, body) = text.split_once('\n').unwrap_or((text, ""));
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    (command, words.collect(), body)
+}
+
+fn number(text: &str) -> Result<usize, String> {
+    text.parse()
+        .map_err(|_| format!("'{}' is not a number", text))
+}
+
+/// A column counted from 1, as the engine's column counted from 0.
+fn column(text: &str) -> Result<usize, String> {
+    number(text)?
+        .checked_sub(1)
+        .ok_or_else(|| "columns start at 1".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::connect4::{Connect4Position, Rules};
+    use crate::engine::tootandotto::{TootOttoPosition, TootOttoRules};
+
+    fn connect4_game() -> OnlineGame {
+        let mut position = Connect4Position::new(Rules::default());
+        assert!(position.play(Move::Drop(3)));
+        assert!(position.play(Move::Drop(4)));
+        let names = vec!["Ann Lee".to_string(), String::new()];
+        OnlineGame::Connect4(Connect4Record::new(&position, names, String::new()))
+    }
+
+    fn toot_otto_game() -> OnlineGame {
+        let mut position = TootOttoPosition::new(TootOttoRules::default());
+        assert!(position.play(2, 'T').is_some());
+        let names = vec!["Ann".to_string(), "Bo".to_string()];
+        OnlineGame::TootOtto(TootOttoRecord::new(&position, names, String::new()))
+    }
+
+    #[test]
+    fn client_messages_read_back_what_they_write() {
+        let messages = [
+            ClientMessage::Create(connect4_game()),
+            ClientMessage::Create(toot_otto_game()),
+            ClientMessage::Join {
+                code: "ABCDE".to_string(),
+                name: "Ann Lee".to_string(),
+            },
+            ClientMessage::Rejoin {
+                code: "ABCDE".to_string(),
+                token: "0123456789abcdef".to_string(),
+            },
+            ClientMessage::Connect4Move(Move::Drop(0)),
+            ClientMessage::Connect4Move(Move::Pop(6)),
+            ClientMessage::TootOttoMove(5, 'O'),
+            ClientMessage::PlayAgain { swap_first: false },
+            ClientMessage::PlayAgain { swap_first: true },
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+    }
+
+    #[test]
+    fn moves_count_columns_from_1() {
+        assert_eq!(
+            ClientMessage::Connect4Move(Move::Drop(0)).to_string(),
+            "drop 1"
+        );
+        assert_eq!(ClientMessage::TootOttoMove(3, 'T').to_string(), "place 4 T");
+        assert_eq!(
+            "pop 7".parse(),
+            Ok(ClientMessage::Connect4Move(Move::Pop(6)))
+        );
+        assert_eq!("place 1 o".parse(), Ok(ClientMessage::TootOttoMove(0, 'O')));
+    }
+
+    #[test]
+    fn malformed_client_messages_are_turned_down() {
+        for text in [
+            "",
+            "hello",
+            "drop",
+            "drop 0",
+            "drop x",
+            "drop 1 2",
+            "pop -1",
+            "place 3",
+            "place 3 X",
+            "place 0 T",
+            "rejoin ABCDE",
+            "again later",
+            "create",
+            "create\nnot a record",
+        ] {
+            assert!(text.parse::<ClientMessage>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn server_messages_read_back_what_they_write() {
+        let messages = [
+            ServerMessage::Joined {
+                code: "ABCDE".to_string(),
+                player: 2,
+                token: "0123456789abcdef".to_string(),
+            },
+            ServerMessage::State {
+                game: connect4_game(),
+                open_seats: 1,
+            },
+            ServerMessage::State {
+                game: toot_otto_game(),
+                open_seats: 0,
+            },
+            ServerMessage::Error("it's not your turn".to_string()),
+        ];
+        for message in messages {
+            assert_eq!(message.to_string().parse(), Ok(message));
+        }
+    }
+
+    #[test]
+    fn malformed_server_messages_are_turned_down() {
+        for text in [
+            "",
+            "hello",
+            "joined ABCDE",
+            "joined ABCDE two 0123",
+            "state",
+            "state x\n",
+            "state 1\nnot a record",
+        ] {
+            assert!(text.parse::<ServerMessage>().is_err(), "{:?}", text);
+        }
+    }
+}
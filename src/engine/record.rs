@@ -0,0 +1,616 @@
+//! Plain-text game records, so a game can be saved and picked up again.
+//!
+//! A record starts with `[Key "Value"]` header lines, the way chess PGN files
+//! do, followed by a blank line and the moves.
+
+use crate::constant::{COMPUTER, MAX_PLAYERS, USER};
+use crate::engine::connect4::{
+    Connect4Position, Move, Rules, Topology, Variant, MAX_COLUMNS, MAX_ROWS, MIN_WIN_LENGTH,
+};
+use crate::engine::tootandotto::{TootOttoPosition, TootOttoRules, MAX_SIZE};
+use std::fmt;
+use std::str::FromStr;
+
+/// The `Game` header of a Connect 4 record.
+const CONNECT4_GAME: &str = "Connect 4";
+
+/// The `Game` header of a TOOT-OTTO record.
+const TOOT_OTTO_GAME: &str = "TOOT-OTTO";
+
+const VARIANTS: [Variant; 4] = [
+    Variant::Standard,
+    Variant::PopOut,
+    Variant::FiveInARow,
+    Variant::PopTen,
+];
+
+/// A Connect 4 game, from the rules to the last move played.
+///
+/// Moves are 1-based column numbers with a `p` in front of a pop, so a game
+/// might start `4453p2`. On a board with ten columns the moves are separated
+/// by spaces, since column 10 takes two digits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Connect4Record {
+    pub rules: Rules,
+    /// One name per player, in turn order.
+    pub names: Vec<String>,
+    /// When the game was played, as YYYY-MM-DD. Left out of the record if
+    /// empty.
+    pub date: String,
+    pub moves: Vec<Move>,
+}
+
+impl Connect4Record {
+    /// Records the game played so far on `position`.
+    pub fn new(position: &Connect4Position, names: Vec<String>, date: String) -> Self {
+        Connect4Record {
+            rules: position.rules(),
+            names,
+            date,
+            moves: position.moves().to_vec(),
+        }
+    }
+
+    /// Replays the moves on a new board.
+    pub fn position(&self) -> Result<Connect4Position, String> {
+        check_rules(&self.rules)?;
+        let mut position = Connect4Position::new(self.rules);
+        for (i, &mv) in self.moves.iter().enumerate() {
+            if position.winner().is_some() || position.is_draw() {
+                return Err(format!(
+                    "move {} ({}) comes after the end of the game",
+                    i + 1,
+                    move_name(mv)
+                ));
+            }
+            if !position.play(mv) {
+                return Err(format!(
+                    "move {} ({}) can't be played",
+                    i + 1,
+                    move_name(mv)
+                ));
+            }
+        }
+        Ok(position)
+    }
+}
+
+impl fmt::Display for Connect4Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_header(f, "Game", CONNECT4_GAME)?;
+        if !self.date.is_empty() {
+            write_header(f, "Date", &self.date)?;
+        }
+        for (i, name) in self.names.iter().enumerate() {
+            write_header(f, &format!("Player{}", i + 1), name)?;
+        }
+        write_header(f, "Rows", &self.rules.rows.to_string())?;
+        write_header(f, "Columns", &self.rules.columns.to_string())?;
+        write_header(f, "WinLength", &self.rules.win_length.to_string())?;
+        write_header(f, "Variant", self.rules.variant.name())?;
+        write_header(f, "Topology", topology_name(self.rules.topology))?;
+        write_header(f, "Players", &self.rules.players.to_string())?;
+        // A record that doesn't replay is still written, as an unfinished game
+        let result = self.position().map_or("*".to_string(), |position| {
+            result(position.winner(), position.is_draw())
+        });
+        write_header(f, "Result", &result)?;
+        writeln!(f)?;
+        let separator = if self.rules.columns > 9 { " " } else { "" };
+        let moves: Vec<String> = self.moves.iter().map(|&mv| move_name(mv)).collect();
+        writeln!(f, "{}", moves.join(separator))
+    }
+}
+
+impl FromStr for Connect4Record {
+    type Err = String;
+
+    /// Reads a record and checks that its moves can be played. Headers that
+    /// are left out take their default values.
+    fn from_str(text: &str) -> Result<Self, String> {
+        let (headers, moves) = parse_headers(text)?;
+        headers.check_game(CONNECT4_GAME)?;
+        let variant = match headers.get("Variant") {
+            Some(name) => *VARIANTS
+                .iter()
+                .find(|variant| variant.name() == name)
+                .ok_or_else(|| format!("unknown variant '{}'", name))?,
+            None => Variant::Standard,
+        };
+        // Some variants come with a board of their own
+        let mut rules = variant.fixed_rules().unwrap_or(Rules {
+            variant,
+            ..Rules::default()
+        });
+        rules.rows = headers.number("Rows")?.unwrap_or(rules.rows);
+        rules.columns = headers.number("Columns")?.unwrap_or(rules.columns);
+        rules.win_length = headers.number("WinLength")?.unwrap_or(rules.win_length);
+        rules.players = headers.number("Players")?.unwrap_or(rules.players);
+        rules.topology = match headers.get("Topology") {
+            Some("Flat") | None => Topology::Flat,
+            Some("Cylinder") => Topology::Cylinder,
+            Some(name) => return Err(format!("unknown topology '{}'", name)),
+        };
+        check_rules(&rules)?;
+
+        let record = Connect4Record {
+            rules,
+            names: headers.names(rules.players),
+            date: headers.get("Date").unwrap_or_default().to_string(),
+            moves: parse_moves(&moves, rules.columns)?,
+        };
+        let position = record.position()?;
+        headers.check_result(result(position.winner(), position.is_draw()))?;
+        Ok(record)
+    }
+}
+
+/// A TOOT-OTTO game, from the rules to the last letter played.
+///
+/// Moves are a 1-based column number and the letter dropped into it,
+/// separated by spaces, as in `3T 4O 3O`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TootOttoRecord {
+    pub rules: TootOttoRules,
+    /// The names of player 1 and player 2.
+    pub names: Vec<String>,
+    /// When the game was played, as YYYY-MM-DD. Left out of the record if
+    /// empty.
+    pub date: String,
+    pub moves: Vec<(usize, char)>,
+}
+
+impl TootOttoRecord {
+    /// Records the game played so far on `position`.
+    pub fn new(position: &TootOttoPosition, names: Vec<String>, date: String) -> Self {
+        TootOttoRecord {
+            rules: position.rules(),
+            names,
+            date,
+            moves: position.moves(),
+        }
+    }
+
+    /// Replays the moves on a new board, checking each column has room and
+    /// each player still has the letter they placed.
+    pub fn position(&self) -> Result<TootOttoPosition, String> {
+        check_toot_otto_rules(&self.rules)?;
+        let mut position = TootOttoPosition::new(self.rules);
+        for (i, &(col, letter)) in self.moves.iter().enumerate() {
+            let name = format!("move {} ({}{})", i + 1, col + 1, letter);
+            let player = position.current_player();
+            if position.winner().is_some() || position.is_draw() {
+                return Err(format!("{} comes after the end of the game", name));
+            }
+            if position.next_open_row(col).is_none() {
+                return Err(format!("{}: column {} is full", name, col + 1));
+            }
+            if position.play(col, letter).is_none() {
+                return Err(format!(
+                    "{}: player {} has no {}'s left",
+                    name, player, letter
+                ));
+            }
+        }
+        Ok(position)
+    }
+}
+
+impl fmt::Display for TootOttoRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_header(f, "Game", TOOT_OTTO_GAME)?;
+        if !self.date.is_empty() {
+            write_header(f, "Date", &self.date)?;
+        }
+        for (i, name) in self.names.iter().enumerate() {
+            write_header(f, &format!("Player{}", i + 1), name)?;
+        }
+        write_header(f, "Rows", &self.rules.rows.to_string())?;
+        write_header(f, "Columns", &self.rules.columns.to_string())?;
+        write_header(f, "Letters", &self.rules.letters.to_string())?;
+        write_header(f, "Toot", &self.rules.toot_player.to_string())?;
+        write_header(f, "First", &self.rules.first_player.to_string())?;
+        let result = self.position().map_or("*".to_string(), |position| {
+            result(position.winner(), position.is_draw())
+        });
+        write_header(f, "Result", &result)?;
+        writeln!(f)?;
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .map(|&(col, letter)| format!("{}{}", col + 1, letter))
+            .collect();
+        writeln!(f, "{}", moves.join(" "))
+    }
+}
+
+impl FromStr for TootOttoRecord {
+    type Err = String;
+
+    /// Reads a record and checks that its moves can be played. Headers that
+    /// are left out take their default values, with enough letters to fill
+    /// the board.
+    fn from_str(text: &str) -> Result<Self, String> {
+        let (headers, moves) = parse_headers(text)?;
+        headers.check_game(TOOT_OTTO_GAME)?;
+        let defaults = TootOttoRules::default();
+        let rows = headers.number("Rows")?.unwrap_or(defaults.rows);
+        let columns = headers.number("Columns")?.unwrap_or(defaults.columns);
+        let mut rules = TootOttoRules::for_board(rows, columns);
+        rules.letters = headers.number("Letters")?.unwrap_or(rules.letters);
+        rules.toot_player = headers.number("Toot")?.unwrap_or(rules.toot_player);
+        rules.first_player = headers.number("First")?.unwrap_or(rules.first_player);
+        check_toot_otto_rules(&rules)?;
+
+        let mut parsed = Vec::new();
+        for token in moves.split_whitespace() {
+            let letter = token
+                .chars()
+                .last()
+                .map(|letter| letter.to_ascii_uppercase())
+                .filter(|letter| ['T', 'O'].contains(letter))
+                .ok_or_else(|| format!("'{}' is not a column and a letter", token))?;
+            let col: usize = token[..token.len() - 1]
+                .parse()
+                .map_err(|_| format!("'{}' is not a column and a letter", token))?;
+            if !(1..=columns).contains(&col) {
+                return Err(format!("there is no column {}", col));
+            }
+            parsed.push((col - 1, letter));
+        }
+        let record = TootOttoRecord {
+            rules,
+            names: headers.names(2),
+            date: headers.get("Date").unwrap_or_default().to_string(),
+            moves: parsed,
+        };
+        let position = record.position()?;
+        headers.check_result(result(position.winner(), position.is_draw()))?;
+        Ok(record)
+    }
+}
+
+/// What `Connect4Position::new` would refuse, as an error instead of a panic.
+fn check_rules(rules: &Rules) -> Result<(), String> {
+    if !(4..=MAX_ROWS).contains(&rules.rows) || !(4..=MAX_COLUMNS).contains(&rules.columns) {
+        return Err(format!(
+            "the board must be between 4x4 and {}x{}",
+            MAX_ROWS, MAX_COLUMNS
+        ));
+    }
+    if !(MIN_WIN_LENGTH..=rules.rows.max(rules.columns)).contains(&rules.win_length) {
+        return Err(format!(
+            "the win length must be between {} and {}",
+            MIN_WIN_LENGTH,
+            rules.rows.max(rules.columns)
+        ));
+    }
+    if !(2..=MAX_PLAYERS).contains(&rules.players) {
+        return Err(format!("between 2 and {} players can play", MAX_PLAYERS));
+    }
+    match rules.variant.fixed_rules() {
+        Some(fixed) if fixed != *rules => {
+            Err(format!("{} has a board of its own", rules.variant.name()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// What `TootOttoPosition::new` would refuse, as an error instead of a panic.
+fn check_toot_otto_rules(rules: &TootOttoRules) -> Result<(), String> {
+    let sizes = 1..=MAX_SIZE;
+    if !sizes.contains(&rules.rows) || !sizes.contains(&rules.columns) {
+        return Err(format!("board sides must be between 1 and {}", MAX_SIZE));
+    }
+    if rules.rows.max(rules.columns) < 4 {
+        return Err("the board has no room for a four-letter word".to_string());
+    }
+    for (key, player) in [("Toot", rules.toot_player), ("First", rules.first_player)] {
+        if player != USER && player != COMPUTER {
+            return Err(format!("{} should be player {} or {}", key, USER, COMPUTER));
+        }
+    }
+    Ok(())
+}
+
+/// The `Result` header: the winner's number, `draw`, or `*` for a game that
+/// is still going.
+fn result(winner: Option<usize>, is_draw: bool) -> String {
+    match winner {
+        Some(winner) => winner.to_string(),
+        None if is_draw => "draw".to_string(),
+        None => "*".to_string(),
+    }
+}
+
+fn topology_name(topology: Topology) -> &'static str {
+    match topology {
+        Topology::Flat => "Flat",
+        Topology::Cylinder => "Cylinder",
+    }
+}
+
+fn move_name(mv: Move) -> String {
+    match mv {
+        Move::Drop(col) => (col + 1).to_string(),
+        Move::Pop(col) => format!("p{}", col + 1),
+    }
+}
+
+fn parse_moves(text: &str, columns: usize) -> Result<Vec<Move>, String> {
+    let mut moves = Vec::new();
+    for token in text.split_whitespace() {
+        let mut rest = token;
+        while !rest.is_empty() {
+            let (is_pop, column) = match rest.strip_prefix(['p', 'P']) {
+                Some(column) => (true, column),
+                None => (false, rest),
+            };
+            // Single-digit columns are run together, so each digit is a move
+            let digits = column
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(column.len());
+            let digits = if columns > 9 { digits } else { digits.min(1) };
+            let col: usize = column[..digits]
+                .parse()
+                .map_err(|_| format!("'{}' is not a move", token))?;
+            if !(1..=columns).contains(&col) {
+                return Err(format!("there is no column {}", col));
+            }
+            moves.push(if is_pop {
+                Move::Pop(col - 1)
+            } else {
+                Move::Drop(col - 1)
+            });
+            rest = &column[digits..];
+        }
+    }
+    Ok(moves)
+}
+
+fn write_header(f: &mut fmt::Formatter, key: &str, value: &str) -> fmt::Result {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(f, "[{} \"{}\"]", key, value)
+}
+
+/// The `(key, value)` headers at the top of a record.
+struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn number(&self, key: &str) -> Result<Option<usize>, String> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("{} should be a number, not '{}'", key, value))
+            })
+            .transpose()
+    }
+
+    fn check_game(&self, game: &str) -> Result<(), String> {
+        if self.get("Game") == Some(game) {
+            Ok(())
+        } else {
+            Err(format!("not a {} record", game))
+        }
+    }
+
+    /// `PlayerN` for each player, or "Player N" for the ones left out.
+    fn names(&self, players: usize) -> Vec<String> {
+        (1..=players)
+            .map(|player| {
+                self.get(&format!("Player{}", player))
+                    .map_or_else(|| format!("Player {}", player), str::to_string)
+            })
+            .collect()
+    }
+
+    /// Whether the `Result` header, if there is one, agrees with the moves.
+    fn check_result(&self, actual: String) -> Result<(), String> {
+        match self.get("Result") {
+            Some(expected) if expected != actual => Err(format!(
+                "the result is {} but the moves end in {}",
+                expected, actual
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Splits a record into its headers and the text of the moves.
+fn parse_headers(text: &str) -> Result<(Headers, String), String> {
+    let mut headers = Vec::new();
+    let mut moves = String::new();
+    for line in text.lines().map(str::trim) {
+        let Some(header) = line.strip_prefix('[') else {
+            moves.push_str(line);
+            moves.push(' ');
+            continue;
+        };
+        let (key, value) = header
+            .strip_suffix(']')
+            .and_then(|header| header.split_once(' '))
+            .and_then(|(key, value)| {
+                let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+                Some((key, value))
+            })
+            .ok_or_else(|| format!("'{}' is not a header like [Key \"Value\"]", line))?;
+        let mut unescaped = String::new();
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            unescaped.push(if c == '\\' {
+                chars.next().unwrap_or(c)
+            } else {
+                c
+            });
+        }
+        headers.push((key.to_string(), unescaped));
+    }
+    Ok((Headers(headers), moves))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connect4_record(rules: Rules, moves: &[Move]) -> Connect4Record {
+        let mut position = Connect4Position::new(rules);
+        for &mv in moves {
+            assert!(position.play(mv));
+        }
+        let names = (1..=rules.players)
+            .map(|player| format!("Player \"{}\"", player))
+            .collect();
+        Connect4Record::new(&position, names, "2024-03-01".to_string())
+    }
+
+    #[test]
+    fn connect4_record_reads_back_what_it_writes() {
+        let record = connect4_record(
+            Rules::default(),
+            &[Move::Drop(3), Move::Drop(3), Move::Drop(4), Move::Drop(2)],
+        );
+        let text = record.to_string();
+        assert!(text.contains("[Result \"*\"]"));
+        assert!(text.ends_with("\n\n4453\n"));
+        assert_eq!(text.parse::<Connect4Record>(), Ok(record));
+    }
+
+    #[test]
+    fn connect4_record_keeps_pops_and_wide_boards() {
+        let rules = Rules {
+            columns: 10,
+            variant: Variant::PopOut,
+            players: 3,
+            ..Rules::default()
+        };
+        let record = connect4_record(
+            rules,
+            &[
+                Move::Drop(9),
+                Move::Drop(0),
+                Move::Drop(4),
+                Move::Drop(9),
+                Move::Pop(0),
+            ],
+        );
+        let text = record.to_string();
+        assert!(text.ends_with("\n\n10 1 5 10 p1\n"));
+        assert_eq!(text.parse::<Connect4Record>(), Ok(record));
+    }
+
+    #[test]
+    fn connect4_record_rejects_moves_that_cant_be_played() {
+        let full = "[Game \"Connect 4\"]\n\n4444444";
+        assert_eq!(
+            full.parse::<Connect4Record>(),
+            Err("move 7 (4) can't be played".to_string())
+        );
+        let pop = "[Game \"Connect 4\"]\n\n4p4";
+        assert_eq!(
+            pop.parse::<Connect4Record>(),
+            Err("move 2 (p4) can't be played".to_string())
+        );
+        let off_the_board = "[Game \"Connect 4\"]\n\n48";
+        assert_eq!(
+            off_the_board.parse::<Connect4Record>(),
+            Err("there is no column 8".to_string())
+        );
+        let after_the_end = "[Game \"Connect 4\"]\n\n12121211";
+        assert_eq!(
+            after_the_end.parse::<Connect4Record>(),
+            Err("move 8 (1) comes after the end of the game".to_string())
+        );
+    }
+
+    #[test]
+    fn connect4_record_checks_the_result_against_the_moves() {
+        let won = "[Game \"Connect 4\"]\n[Result \"1\"]\n\n1212121";
+        assert!(won.parse::<Connect4Record>().is_ok());
+        let wrong_winner = "[Game \"Connect 4\"]\n[Result \"2\"]\n\n1212121";
+        assert_eq!(
+            wrong_winner.parse::<Connect4Record>(),
+            Err("the result is 2 but the moves end in 1".to_string())
+        );
+        let unfinished = "[Game \"Connect 4\"]\n[Result \"draw\"]\n\n121212";
+        assert_eq!(
+            unfinished.parse::<Connect4Record>(),
+            Err("the result is draw but the moves end in *".to_string())
+        );
+    }
+
+    #[test]
+    fn toot_otto_record_reads_back_what_it_writes() {
+        let rules = TootOttoRules {
+            toot_player: COMPUTER,
+            first_player: COMPUTER,
+            ..TootOttoRules::default()
+        };
+        let mut position = TootOttoPosition::new(rules);
+        for (col, letter) in [(0, 'T'), (1, 'O'), (1, 'O')] {
+            assert!(position.play(col, letter).is_some());
+        }
+        let names = vec!["Ann".to_string(), "Bo \\ \"B\"".to_string()];
+        let record = TootOttoRecord::new(&position, names, String::new());
+        let text = record.to_string();
+        assert!(!text.contains("[Date"));
+        assert!(text.ends_with("\n\n1T 2O 2O\n"));
+        assert_eq!(text.parse::<TootOttoRecord>(), Ok(record));
+    }
+
+    #[test]
+    fn toot_otto_record_rejects_moves_that_cant_be_played() {
+        let full = "[Game \"TOOT-OTTO\"]\n\n1T 1O 1T 1O 1T";
+        assert_eq!(
+            full.parse::<TootOttoRecord>(),
+            Err("move 5 (1T): column 1 is full".to_string())
+        );
+        let no_letters_left = "[Game \"TOOT-OTTO\"]\n[Letters \"1\"]\n\n1T 2T 3T";
+        assert_eq!(
+            no_letters_left.parse::<TootOttoRecord>(),
+            Err("move 3 (3T): player 1 has no T's left".to_string())
+        );
+        let off_the_board = "[Game \"TOOT-OTTO\"]\n\n1T 7O";
+        assert_eq!(
+            off_the_board.parse::<TootOttoRecord>(),
+            Err("there is no column 7".to_string())
+        );
+        let not_a_letter = "[Game \"TOOT-OTTO\"]\n\n1X";
+        assert_eq!(
+            not_a_letter.parse::<TootOttoRecord>(),
+            Err("'1X' is not a column and a letter".to_string())
+        );
+    }
+
+    #[test]
+    fn toot_otto_record_checks_the_result_against_the_moves() {
+        let won = "[Game \"TOOT-OTTO\"]\n[Result \"1\"]\n\n1T 2O 3O 4T";
+        assert!(won.parse::<TootOttoRecord>().is_ok());
+        let wrong_winner = "[Game \"TOOT-OTTO\"]\n[Result \"2\"]\n\n1T 2O 3O 4T";
+        assert_eq!(
+            wrong_winner.parse::<TootOttoRecord>(),
+            Err("the result is 2 but the moves end in 1".to_string())
+        );
+        let after_the_end = "[Game \"TOOT-OTTO\"]\n\n1T 2O 3O 4T 5T";
+        assert_eq!(
+            after_the_end.parse::<TootOttoRecord>(),
+            Err("move 5 (5T) comes after the end of the game".to_string())
+        );
+    }
+
+    #[test]
+    fn connect4_record_rejects_other_games() {
+        let text = "[Game \"TOOT-OTTO\"]\n\n1T";
+        assert_eq!(
+            text.parse::<Connect4Record>(),
+            Err("not a Connect 4 record".to_string())
+        );
+    }
+}
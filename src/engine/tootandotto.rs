@@ -0,0 +1,689 @@
+use crate::constant::{COMPUTER, DEFAULT_OT_COLS, DEFAULT_OT_LETTERS, DEFAULT_OT_ROWS, USER};
+use rand::prelude::*;
+
+/// Largest board either side may have.
+pub const MAX_SIZE: usize = 10;
+
+const TOOT: [char; 4] = ['T', 'O', 'O', 'T'];
+const OTTO: [char; 4] = ['O', 'T', 'T', 'O'];
+
+/// The word a player is trying to spell. Both players place both letters,
+/// so who spelled a word is never recorded, only which word it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Word {
+    Toot,
+    Otto,
+}
+
+impl Word {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Word::Toot => "TOOT",
+            Word::Otto => "OTTO",
+        }
+    }
+
+    fn letters(&self) -> &'static [char; 4] {
+        match self {
+            Word::Toot => &TOOT,
+            Word::Otto => &OTTO,
+        }
+    }
+
+    fn other(&self) -> Word {
+        match self {
+            Word::Toot => Word::Otto,
+            Word::Otto => Word::Toot,
+        }
+    }
+}
+
+/// Board size, how many letters each player starts with and who spells
+/// which word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TootOttoRules {
+    pub rows: usize,
+    pub columns: usize,
+    /// How many T's each player starts with, and how many O's.
+    pub letters: usize,
+    /// The player trying to spell TOOT. The other one is OTTO.
+    pub toot_player: usize,
+    /// The player who places the first letter.
+    pub first_player: usize,
+}
+
+impl TootOttoRules {
+    /// Rules for any board, with just enough letters for the two players to
+    /// fill it between them.
+    pub fn for_board(rows: usize, columns: usize) -> Self {
+        TootOttoRules {
+            rows,
+            columns,
+            letters: (rows * columns).div_ceil(4),
+            toot_player: USER,
+            first_player: USER,
+        }
+    }
+}
+
+impl Default for TootOttoRules {
+    fn default() -> Self {
+        TootOttoRules {
+            rows: DEFAULT_OT_ROWS,
+            columns: DEFAULT_OT_COLS,
+            letters: DEFAULT_OT_LETTERS,
+            toot_player: USER,
+            first_player: USER,
+        }
+    }
+}
+
+/// A TOOT-OTTO game position, independent of any UI.
+///
+/// Each cell holds the letter and the player who placed it, with `' '` for
+/// an empty cell. Row 0 is the top of the board, matching how the board is
+/// rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TootOttoPosition {
+    rows: usize,
+    columns: usize,
+    /// How many of each letter the players started with.
+    letters: usize,
+    toot_player: usize,
+    first_player: usize,
+    board: Vec<Vec<(char, usize)>>,
+    /// Letters each player has left, indexed by `player - 1`.
+    t_left: [usize; 2],
+    o_left: [usize; 2],
+    moves: Vec<(usize, usize)>,
+}
+
+impl TootOttoPosition {
+    pub fn new(rules: TootOttoRules) -> Self {
+        let TootOttoRules {
+            rows,
+            columns,
+            letters,
+            toot_player,
+            first_player,
+        } = rules;
+        assert!(
+            (1..=MAX_SIZE).contains(&rows) && (1..=MAX_SIZE).contains(&columns),
+            "board sides must be between 1 and {}",
+            MAX_SIZE
+        );
+        assert!(rows.max(columns) >= 4, "the board has no room for a four-letter word");
+        assert!(
+            toot_player == USER || toot_player == COMPUTER,
+            "TOOT must be played by one of the two players"
+        );
+        assert!(
+            first_player == USER || first_player == COMPUTER,
+            "one of the two players must go first"
+        );
+        TootOttoPosition {
+            rows,
+            columns,
+            letters,
+            toot_player,
+            first_player,
+            board: vec![vec![(' ', 0); columns]; rows],
+            t_left: [letters; 2],
+            o_left: [letters; 2],
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn rules(&self) -> TootOttoRules {
+        TootOttoRules {
+            rows: self.rows,
+            columns: self.columns,
+            letters: self.letters,
+            toot_player: self.toot_player,
+            first_player: self.first_player,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// The word `player` is trying to spell.
+    pub fn word(&self, player: usize) -> Word {
+        if player == self.toot_player {
+            Word::Toot
+        } else {
+            Word::Otto
+        }
+    }
+
+    /// The player trying to spell `word`.
+    pub fn player_for(&self, word: Word) -> usize {
+        match word {
+            Word::Toot => self.toot_player,
+            Word::Otto => self.opponent(self.toot_player),
+        }
+    }
+
+    fn opponent(&self, player: usize) -> usize {
+        if player == USER {
+            COMPUTER
+        } else {
+            USER
+        }
+    }
+
+    /// The letter in a cell and the player who placed it.
+    pub fn cell(&self, row: usize, col: usize) -> (char, usize) {
+        self.board[row][col]
+    }
+
+    /// The player whose letter goes in next.
+    pub fn current_player(&self) -> usize {
+        if self.moves.len().is_multiple_of(2) {
+            self.first_player
+        } else {
+            self.opponent(self.first_player)
+        }
+    }
+
+    /// How many of `letter` `player` has left to place.
+    pub fn letters_left(&self, player: usize, letter: char) -> usize {
+        match letter {
+            'T' => self.t_left[player - 1],
+            'O' => self.o_left[player - 1],
+            _ => 0,
+        }
+    }
+
+    /// Every `(column, letter)` played so far, oldest first.
+    pub fn moves(&self) -> Vec<(usize, char)> {
+        self.moves
+            .iter()
+            .map(|&(col, row)| (col, self.board[row][col].0))
+            .collect()
+    }
+
+    /// The `(col, row)` of the most recent letter, if any.
+    pub fn last_move(&self) -> Option<(usize, usize)> {
+        self.moves.last().copied()
+    }
+
+    pub fn next_open_row(&self, col: usize) -> Option<usize> {
+        if col >= self.columns {
+            return None;
+        }
+        (0..self.rows).rev().find(|&row| self.board[row][col].0 == ' ')
+    }
+
+    pub fn is_legal(&self, col: usize, letter: char) -> bool {
+        self.next_open_row(col).is_some() && self.letters_left(self.current_player(), letter) > 0
+    }
+
+    /// Every `(column, letter)` the player to move may play.
+    pub fn legal_moves(&self) -> Vec<(usize, char)> {
+        (0..self.columns)
+            .flat_map(|col| ['T', 'O'].map(|letter| (col, letter)))
+            .filter(|&(col, letter)| self.is_legal(col, letter))
+            .collect()
+    }
+
+    /// Drops `letter` into `col` for the current player, returning the row
+    /// it landed on, or `None` if the column is full or the player has no
+    /// such letter left.
+    pub fn play(&mut self, col: usize, letter: char) -> Option<usize> {
+        if !self.is_legal(col, letter) {
+            return None;
+        }
+        let row = self.next_open_row(col)?;
+        let player = self.current_player();
+        self.board[row][col] = (letter, player);
+        self.inventory(letter)[player - 1] -= 1;
+        self.moves.push((col, row));
+        Some(row)
+    }
+
+    /// Takes back the most recent letter and returns it to its owner.
+    pub fn undo(&mut self) -> Option<(usize, char)> {
+        let (col, row) = self.moves.pop()?;
+        let (letter, player) = self.board[row][col];
+        self.board[row][col] = (' ', 0);
+        self.inventory(letter)[player - 1] += 1;
+        Some((col, letter))
+    }
+
+    fn inventory(&mut self, letter: char) -> &mut [usize; 2] {
+        if letter == 'T' {
+            &mut self.t_left
+        } else {
+            &mut self.o_left
+        }
+    }
+
+    /// The player whose word is on the board, whoever spelled it. Spelling
+    /// both words at once is a draw.
+    pub fn winner(&self) -> Option<usize> {
+        match (self.contains_word(&TOOT), self.contains_word(&OTTO)) {
+            (true, false) => Some(self.player_for(Word::Toot)),
+            (false, true) => Some(self.player_for(Word::Otto)),
+            _ => None,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.board.iter().all(|row| row.iter().all(|(c, _)| *c != ' '))
+    }
+
+    /// Whether the game ended without a winner: both words were spelled at
+    /// once, the board is full or the player to move is out of letters.
+    pub fn is_draw(&self) -> bool {
+        if self.winner().is_some() {
+            return false;
+        }
+        (self.contains_word(&TOOT) && self.contains_word(&OTTO))
+            || self.is_full()
+            || self.legal_moves().is_empty()
+    }
+
+    fn contains_word(&self, word: &[char]) -> bool {
+        let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+        (0..self.rows).any(|y| {
+            (0..self.columns).any(|x| {
+                self.board[y][x].0 != ' '
+                    && directions
+                        .iter()
+                        .any(|&(dy, dx)| self.check_sequence(x, y, dx, dy, word))
+            })
+        })
+    }
+
+    fn check_sequence(&self, x: usize, y: usize, dx: isize, dy: isize, sequence: &[char]) -> bool {
+        for (index, &item) in sequence.iter().enumerate() {
+            let nx = x as isize + index as isize * dx;
+            let ny = y as isize + index as isize * dy;
+
+            if nx < 0
+                || nx >= self.columns as isize
+                || ny < 0
+                || ny >= self.rows as isize
+                || self.board[ny as usize][nx as usize].0 != item
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// What `evaluate_board` is made of, so that searches can be tuned against
+/// each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TootOttoWeights {
+    /// A window spelling the word.
+    pub win: isize,
+    /// A window where the word is blocked, or has to be.
+    pub block: isize,
+    /// A window still open to the word.
+    pub advance: isize,
+    /// A window where the word is partly blocked.
+    pub block_advance: isize,
+}
+
+impl Default for TootOttoWeights {
+    fn default() -> Self {
+        TootOttoWeights {
+            win: 100,
+            block: 90,
+            advance: 10,
+            block_advance: 10,
+        }
+    }
+}
+
+/// Scored for a finished game, far above anything `evaluate_board` returns.
+const TERMINAL_SCORE: isize = 1_000_000;
+
+/// How deep the computer looks ahead on Hard.
+const SEARCH_DEPTH: usize = 5;
+
+fn check_sequence_score(
+    position: &TootOttoPosition,
+    x: usize,
+    y: usize,
+    dx: isize,
+    dy: isize,
+    sequence: &[char],
+    weights: &TootOttoWeights,
+) -> isize {
+    let mut score = 0;
+    let mut match_count = 0;
+    let mut empty_count = 0;
+    let mut opponent_count = 0;
+
+    for (index, &char) in sequence.iter().enumerate() {
+        let nx = x as isize + index as isize * dx;
+        let ny = y as isize + index as isize * dy;
+
+        if nx < 0 || nx >= position.columns as isize || ny < 0 || ny >= position.rows as isize {
+            continue;
+        }
+
+        if position.board[ny as usize][nx as usize].0 == char {
+            match_count += 1;
+        } else if position.board[ny as usize][nx as usize].0 == ' ' {
+            empty_count += 1;
+        } else {
+            opponent_count += 1;
+        }
+    }
+
+    if opponent_count == 4 {
+        score -= weights.win * 2;
+    } else {
+        match match_count {
+            4 => {
+                score += weights.win;
+            }
+            3 => {
+                // OTT_ (Good) vs. OTTT (Bad)
+                if empty_count == 1 {
+                    score += weights.advance; // Increase score for winning sequences
+                } else {
+                    score -= weights.block;
+                }
+            }
+            2 => {
+                // OT__, _TT_, O__O, __TO
+                if empty_count == 2 {
+                    score += weights.advance; // Increase score for winning sequences
+                } else {
+                    // OTO_, OTTT, OOTO
+                    score -= weights.block_advance * 4;
+                }
+            }
+            1 => {
+                // _T__, __T_, T___, T__T
+                if empty_count == 3 {
+                    score += weights.advance;
+                } else if empty_count == 2 && opponent_count == 1 {
+                    // _TT_, T_TO, TO_T
+                    score -= weights.block_advance * 2;
+                } else if empty_count == 1 && opponent_count == 3 {
+                    // TTT_, _TTT, T_TT
+                    score -= weights.block;
+                } else if empty_count == 0 && opponent_count == 3 {
+                    score += weights.block;
+                }
+            }
+            0 => {
+                if empty_count == 4 {
+                    score += weights.advance;
+                } else if opponent_count == 3 {
+                    // TTT_, _TTT, T_TT
+                    score -= weights.block;
+                } else if opponent_count == 4 {
+                    score -= weights.win * 3;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    score
+}
+
+/// Scores the board for `player`: progress towards their own word counts
+/// for them, and progress towards the opponent's word counts against them.
+fn evaluate_board(position: &TootOttoPosition, player: usize, weights: &TootOttoWeights) -> isize {
+    let own = position.word(player);
+    let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+    let mut score = 0;
+    for y in 0..position.rows {
+        for x in 0..position.columns {
+            for &(dy, dx) in &directions {
+                score += check_sequence_score(position, x, y, dx, dy, own.letters(), weights);
+                score -=
+                    check_sequence_score(position, x, y, dx, dy, own.other().letters(), weights);
+            }
+        }
+    }
+    score
+}
+
+/// Scores `position` for the player to move, looking `depth` letters ahead.
+/// Moves come from the real inventories, so a side that has run out of T's
+/// only ever places O's and vice versa.
+fn negamax(
+    position: &mut TootOttoPosition,
+    depth: usize,
+    mut alpha: isize,
+    beta: isize,
+    weights: &TootOttoWeights,
+) -> isize {
+    let player = position.current_player();
+    if let Some(winner) = position.winner() {
+        // Prefer quick wins and slow losses
+        let score = TERMINAL_SCORE + depth as isize;
+        return if winner == player { score } else { -score };
+    }
+    if position.is_draw() {
+        return 0;
+    }
+    if depth == 0 {
+        return evaluate_board(position, player, weights);
+    }
+
+    let mut best_value = -TERMINAL_SCORE * 2;
+    for (col, letter) in position.legal_moves() {
+        position.play(col, letter);
+        let value = -negamax(position, depth - 1, -beta, -alpha, weights);
+        position.undo();
+
+        best_value = best_value.max(value);
+        alpha = alpha.max(value);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best_value
+}
+
+/// The Hard computer's `(column, letter)` for the player to move, or `None`
+/// if it has nothing to play.
+pub fn best_move(position: &TootOttoPosition) -> Option<(usize, char)> {
+    best_move_with(position, SEARCH_DEPTH, &TootOttoWeights::default())
+}
+
+/// Like [`best_move`], looking `depth` letters ahead and scoring the
+/// positions there with `weights`.
+pub fn best_move_with(
+    position: &TootOttoPosition,
+    depth: usize,
+    weights: &TootOttoWeights,
+) -> Option<(usize, char)> {
+    let mut position = position.clone();
+    let mut alpha = -TERMINAL_SCORE * 2;
+    let mut best = None;
+    for (col, letter) in position.legal_moves() {
+        position.play(col, letter);
+        let value = -negamax(
+            &mut position,
+            depth.max(1) - 1,
+            -TERMINAL_SCORE * 2,
+            -alpha,
+            weights,
+        );
+        position.undo();
+
+        if best.is_none() || value > alpha {
+            alpha = value;
+            best = Some((col, letter));
+        }
+    }
+    best
+}
+
+/// The Easy computer's `(column, letter)`: any legal move at random.
+pub fn random_move(position: &TootOttoPosition) -> Option<(usize, char)> {
+    position
+        .legal_moves()
+        .choose(&mut rand::thread_rng())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays moves written as a column counted from 0 and a letter, such as
+    /// `"0T 1O"`, checking each one is legal.
+    fn play_letters(position: &mut TootOttoPosition, moves: &str) {
+        for mv in moves.split_whitespace() {
+            let (col, letter) = mv.split_at(mv.len() - 1);
+            let letter = letter.chars().next().unwrap();
+            assert!(
+                position.play(col.parse().unwrap(), letter).is_some(),
+                "{}",
+                mv
+            );
+        }
+    }
+
+    fn position_after(rules: TootOttoRules, moves: &str) -> TootOttoPosition {
+        let mut position = TootOttoPosition::new(rules);
+        play_letters(&mut position, moves);
+        position
+    }
+
+    #[test]
+    fn undo_takes_back_letters_and_returns_them() {
+        let start = TootOttoPosition::new(TootOttoRules::default());
+        let mut position = start.clone();
+        play_letters(&mut position, "2T 2O 3O 5T");
+        assert_eq!(position.cell(3, 2), ('T', USER));
+        assert_eq!(position.cell(2, 2), ('O', COMPUTER));
+        assert_eq!(position.letters_left(USER, 'T'), DEFAULT_OT_LETTERS - 1);
+        assert_eq!(position.letters_left(COMPUTER, 'T'), DEFAULT_OT_LETTERS - 1);
+        assert_eq!(position.moves(), [(2, 'T'), (2, 'O'), (3, 'O'), (5, 'T')]);
+
+        assert_eq!(position.undo(), Some((5, 'T')));
+        assert_eq!(position.letters_left(COMPUTER, 'T'), DEFAULT_OT_LETTERS);
+        assert_eq!(position.current_player(), COMPUTER);
+        for _ in 0..3 {
+            assert!(position.undo().is_some());
+        }
+        assert_eq!(position.undo(), None);
+        assert_eq!(position, start);
+    }
+
+    #[test]
+    fn a_player_out_of_a_letter_can_only_place_the_other() {
+        let mut position =
+            position_after(TootOttoRules::for_board(4, 4), "0T 1O 0T 1O 0T 1O 0T 2O");
+        assert_eq!(position.letters_left(USER, 'T'), 0);
+        assert!(!position.is_legal(3, 'T'));
+        assert_eq!(position.play(3, 'T'), None);
+        assert!(position
+            .legal_moves()
+            .iter()
+            .all(|&(col, letter)| col != 0 && letter == 'O'));
+        // Running out of one letter leaves the game going
+        assert!(!position.is_draw());
+        assert_eq!(position.next_open_row(0), None);
+        assert_eq!(position.play(0, 'O'), None);
+    }
+
+    #[test]
+    fn a_full_board_without_either_word_is_a_draw() {
+        let mut position = TootOttoPosition::new(TootOttoRules::for_board(4, 4));
+        play_letters(
+            &mut position,
+            "1T 3T 3T 2O 3O 2T 0T 0T 2O 3O 1T 1O 1O 0O 0O",
+        );
+        assert!(!position.is_full());
+        assert!(!position.is_draw());
+        play_letters(&mut position, "2T");
+        assert!(position.is_full());
+        assert_eq!(position.winner(), None);
+        assert!(position.is_draw());
+        assert!(position.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn letters_outside_the_board_are_turned_down() {
+        let mut position = TootOttoPosition::new(TootOttoRules::default());
+        assert_eq!(position.play(DEFAULT_OT_COLS, 'T'), None);
+        assert_eq!(position.play(0, 'X'), None);
+        assert!(position.moves().is_empty());
+    }
+
+    #[test]
+    fn a_word_is_credited_to_its_side_whoever_spells_it() {
+        // Player 2 spells TOOT with the last letter, which wins for player 1
+        let position = position_after(TootOttoRules::default(), "0T 1O 2O 3T");
+        assert_eq!(position.word(USER), Word::Toot);
+        assert_eq!(position.player_for(Word::Otto), COMPUTER);
+        assert_eq!(position.winner(), Some(USER));
+        assert!(!position.is_draw());
+
+        let rules = TootOttoRules {
+            toot_player: COMPUTER,
+            ..TootOttoRules::default()
+        };
+        let position = position_after(rules, "0T 1O 2O 3T");
+        assert_eq!(position.word(USER), Word::Otto);
+        assert_eq!(position.winner(), Some(COMPUTER));
+
+        // Vertical OTTO, spelled by both players
+        let position = position_after(TootOttoRules::default(), "0O 0T 0T 0O");
+        assert_eq!(position.winner(), Some(COMPUTER));
+    }
+
+    #[test]
+    fn spelling_both_words_at_once_is_a_draw() {
+        let mut position = position_after(TootOttoRules::default(), "4O 1T 3O 5T 0O");
+        assert_eq!(position.winner(), None);
+        assert!(!position.is_draw());
+        // OTTOOT along the bottom row
+        play_letters(&mut position, "2T");
+        assert_eq!(position.winner(), None);
+        assert!(position.is_draw());
+    }
+
+    #[test]
+    fn search_takes_an_immediate_win() {
+        // TOO_ along the bottom row, while an O on column 5 would spell OTTO
+        let position = position_after(TootOttoRules::default(), "0T 5O 1O 5T 2O 5T");
+        assert_eq!(best_move(&position), Some((3, 'T')));
+        assert_eq!(
+            best_move_with(&position, 1, &TootOttoWeights::default()),
+            Some((3, 'T'))
+        );
+    }
+
+    #[test]
+    fn search_blocks_an_immediate_loss() {
+        // _OTT_ along the bottom row: an O on column 4 spells OTTO, so only
+        // a T there stops it
+        let position = position_after(TootOttoRules::default(), "1O 2T 5O 3T");
+        assert_eq!(best_move(&position), Some((4, 'T')));
+        assert_eq!(
+            best_move_with(&position, 2, &TootOttoWeights::default()),
+            Some((4, 'T'))
+        );
+    }
+
+    #[test]
+    fn search_has_nothing_to_play_once_the_game_is_over() {
+        let position = position_after(
+            TootOttoRules::for_board(4, 4),
+            "1T 3T 3T 2O 3O 2T 0T 0T 2O 3O 1T 1O 1O 0O 0O 2T",
+        );
+        assert_eq!(best_move(&position), None);
+        assert_eq!(random_move(&position), None);
+    }
+}
@@ -0,0 +1,48 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: usize,
+    pub score: isize,
+    pub bound: Bound,
+    pub best_move: usize,
+}
+
+/// A fixed-size hash table of search results keyed by Zobrist hash. Each key
+/// maps to a single slot; a new result replaces whatever lived there unless
+/// the slot holds a deeper search of the same position.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    mask: usize,
+}
+
+impl TranspositionTable {
+    /// Creates a table with `2^size_log2` slots.
+    pub fn new(size_log2: u32) -> Self {
+        let size = 1 << size_log2;
+        TranspositionTable {
+            entries: vec![None; size],
+            mask: size - 1,
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[key as usize & self.mask].filter(|entry| entry.key == key)
+    }
+
+    pub fn store(&mut self, entry: Entry) {
+        let slot = &mut self.entries[entry.key as usize & self.mask];
+        if let Some(existing) = slot {
+            if existing.key == entry.key && existing.depth > entry.depth {
+                return;
+            }
+        }
+        *slot = Some(entry);
+    }
+}
@@ -1,30 +1,29 @@
+// The `check` feature builds the web board natively too, so that it is
+// type-checked and linted along with everything else
+#[cfg(any(target_arch = "wasm32", feature = "check"))]
 mod components;
 mod constant;
+#[cfg(not(target_arch = "wasm32"))]
 mod cli;
+mod engine;
 
-use crate::components::app::App;
-use std::{io, process::{self, exit}};
+#[cfg(any(target_arch = "wasm32", feature = "check"))]
+fn render_app() {
+    yew::Renderer::<components::app::App>::new().render();
+}
 
+/// The web build only ever renders the app, and never reads stdin.
+#[cfg(target_arch = "wasm32")]
 fn main() {
+    render_app();
+}
 
-    println!("Would you like to enable debugging? (y/n)");
-    let mut debug_input = String::new();
-    io::stdin().read_line(&mut debug_input).expect("Failed to read line");
-    debug_input = debug_input.trim().to_lowercase();
-
-    if debug_input == "y" {
-        println!("Would game would you like to debug? (A: Connect4 / B: TOOT&OTTO)");
-        let mut game_choice = String::new();
-        io::stdin().read_line(&mut game_choice).expect("Failed to read line");
-        let game_choice = game_choice.trim().to_lowercase();
-        if game_choice == "a" {
-            // Run Connect4
-            cli::connect4();
-        } else if game_choice == "b" {
-            // Run Toot & Otto
-        }
-        process::exit(1);
-    }
-
-    yew::Renderer::<App>::new().render();
+/// The native build is a command-line tool, see `cli::run`.
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    // Named but never called, so that the `check` build finds the same dead
+    // code in the web board as the web build does
+    #[cfg(feature = "check")]
+    let _ = render_app;
+    cli::run();
 }
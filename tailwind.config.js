@@ -12,6 +12,8 @@ module.exports = {
         boardPrimaryBg: 'var(--boardPrimaryBg)',
         chipPrimaryBg: 'var(--chipPrimaryBg)',
         chipSecondaryBg: 'var(--chipSecondaryBg)',
+        chipTertiaryBg: 'var(--chipTertiaryBg)',
+        chipQuaternaryBg: 'var(--chipQuaternaryBg)',
       },
     },
   },